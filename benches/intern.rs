@@ -0,0 +1,72 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use json_flat_parser::key_pool::KeyPool;
+use json_flat_parser::{JSONParser, ParseOptions};
+
+// run: cargo bench --bench intern
+// 1000000 rows with a column of 10 distinct values, object raw data not kept, parsed with parse_owned_with_pool
+// requested bytes retained by the result, pool included - allocations made while parsing - time in ms
+// intern_values(false): 295.3mb - 5000043 - 2504
+// intern_values(true):  271.3mb - 4000056 - 2352
+// the pool holds the 2000001 distinct pointers of the array in both cases, interning values saves one allocation per row
+
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const COUNTRIES: [&str; 10] = ["FR", "DE", "ES", "IT", "BE", "NL", "PT", "PL", "SE", "AT"];
+
+fn categorical_column(rows: usize) -> String {
+    let mut json = String::with_capacity(rows * 20);
+    json.push('[');
+    for i in 0..rows {
+        if i > 0 {
+            json.push(',');
+        }
+        json.push_str(&format!(r#"{{"country": "{}"}}"#, COUNTRIES[i % COUNTRIES.len()]));
+    }
+    json.push(']');
+    json
+}
+
+fn intern(c: &mut Criterion) {
+    let json = categorical_column(1_000_000);
+    let options = |intern_values: bool| ParseOptions::default().keep_object_raw_data(false).intern_values(intern_values);
+
+    for intern_values in [false, true] {
+        let heap = ALLOCATED.load(Ordering::Relaxed);
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+        let mut pool = KeyPool::new();
+        let res = JSONParser::parse_owned_with_pool(&json, options(intern_values), &mut pool).unwrap();
+        println!("intern_values({}): {} bytes retained, {} allocations, {} distinct values", intern_values, ALLOCATED.load(Ordering::Relaxed) - heap, ALLOCATIONS.load(Ordering::Relaxed) - allocations, pool.value_count());
+        drop(res);
+    }
+
+    let mut group = c.benchmark_group("intern");
+    group.sample_size(10);
+    group.bench_function("parse_owned_with_pool", |b| b.iter(|| JSONParser::parse_owned_with_pool(black_box(&json), options(false), &mut KeyPool::new()).unwrap()));
+    group.bench_function("parse_owned_with_pool_intern_values", |b| b.iter(|| JSONParser::parse_owned_with_pool(black_box(&json), options(true), &mut KeyPool::new()).unwrap()));
+    group.finish();
+}
+
+criterion_group!(benches, intern);
+criterion_main!(benches);
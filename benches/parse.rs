@@ -0,0 +1,43 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use json_flat_parser::{JSONParser, ParseOptions, ParseResultOwned};
+
+// run: cargo bench --bench parse
+// 20000 rows (~2.6mb), time in ms
+// full - max_depth 1 - to_owned
+// 64.8 - 19.8 - 98.9: initial
+// 33.7 - 13.9 - 59.3: single buffer pointer fragment instead of a Vec<String> of segments
+// 35.4 - 15.0 - 49.8: look for both opening and closing char when skipping containers. Projection of 2 fields: 16.4
+// 38.8 - 22.9 - 60.2: skip strings when skipping containers, brackets inside strings were counted. Projection of 2 fields: 15.8
+// ParseResultOwned::from_bytes of the full parse, an alternative to to_owned when caching: 26.8
+// JSONParser::validate_stream, byte by byte state machine without allocation: 21.5
+
+fn homogeneous_array(rows: usize) -> String {
+    let mut json = String::with_capacity(rows * 160);
+    json.push('[');
+    for i in 0..rows {
+        if i > 0 {
+            json.push(',');
+        }
+        json.push_str(&format!(r#"{{"id": {}, "name": "item number {}", "price": {}.5, "active": {}, "tags": ["a", "b", "c"], "owner": {{"id": {}, "country": "FR"}}}}"#,
+                               i, i, i % 1000, i % 2 == 0, i % 97));
+    }
+    json.push(']');
+    json
+}
+
+fn parse(c: &mut Criterion) {
+    let json = homogeneous_array(20_000);
+    let mut group = c.benchmark_group("parse");
+    group.throughput(Throughput::Bytes(json.len() as u64));
+    group.bench_function("homogeneous_array", |b| b.iter(|| JSONParser::parse(black_box(&json), ParseOptions::default()).unwrap()));
+    group.bench_function("homogeneous_array_max_depth_1", |b| b.iter(|| JSONParser::parse(black_box(&json), ParseOptions::default().max_depth(1)).unwrap()));
+    group.bench_function("homogeneous_array_projection", |b| b.iter(|| JSONParser::parse_projection(black_box(&json), &["/*/id", "/*/owner/country"], ParseOptions::default()).unwrap()));
+    group.bench_function("homogeneous_array_to_owned", |b| b.iter(|| JSONParser::parse(black_box(&json), ParseOptions::default()).unwrap().to_owned()));
+    let bytes = JSONParser::parse(&json, ParseOptions::default()).unwrap().to_bytes();
+    group.bench_function("homogeneous_array_from_bytes", |b| b.iter(|| ParseResultOwned::from_bytes(black_box(&bytes)).unwrap()));
+    group.bench_function("homogeneous_array_validate_stream", |b| b.iter(|| JSONParser::validate_stream(black_box(json.as_bytes())).unwrap()));
+    group.finish();
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
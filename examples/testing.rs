@@ -20,6 +20,6 @@ fn swar() {
     };
     println!("Position of {{ {:?}", position);
 
-    let i = high_bit_mask1 == 0 && str[0] & 0x01 * b'{' != 0x01 * b'{';
+    let i = high_bit_mask1 == 0 && str[0] & b'{' != b'{';
     println!("{}", i);
 }
\ No newline at end of file
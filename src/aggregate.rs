@@ -0,0 +1,126 @@
+use std::collections::HashSet;
+
+use crate::error::ParseError;
+use crate::{array_element_index, JSONParser, ParseOptions, ValueType};
+
+/// Aggregate of a column, see [`JSONParser::aggregate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Agg {
+    Sum,
+    /// Rows having a non null value.
+    Count,
+    Min,
+    Max,
+    /// Distinct non null values, compared as written: `1` and `1.0` are distinct, `1` and `"1"` too.
+    DistinctCount,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AggResult {
+    /// Pointer of the column relative to an element, as given.
+    pub pointer: String,
+    pub agg: Agg,
+    /// `None` for `Sum`, `Min` and `Max` when no row has a numeric value, counts being always set.
+    pub value: Option<f64>,
+    /// Rows having a value which was not aggregated: a non numeric one for `Sum`, `Min` and `Max`, `null` for counts. Rows without the column are not counted.
+    pub skipped: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AggResults {
+    /// In the order of the requested aggregates.
+    pub results: Vec<AggResult>,
+}
+
+impl AggResults {
+    pub fn get(&self, pointer: &str, agg: Agg) -> Option<&AggResult> {
+        self.results.iter().find(|result| result.pointer == pointer && result.agg == agg)
+    }
+}
+
+impl JSONParser {
+    /// Fold columns of the array at `prefix`, `""` being the root array, into aggregates, e.g. the sum of `/price` over `/items`, flattening elements one at a time like `parse_array_filtered`:
+    /// entries of each element are dropped once folded. `aggs` pairs a pointer relative to an element with an aggregate.
+    /// Numeric aggregates parse the raw number as a `f64`, columns holding a container are aggregated like any non numeric value.
+    pub fn aggregate(input: &str, prefix: &str, aggs: &[(&str, Agg)], options: ParseOptions) -> Result<AggResults, ParseError> {
+        let prefix = if prefix == "/" { "" } else { prefix };
+        let columns: Vec<String> = aggs.iter().map(|(pointer, _)| {
+            let pointer = pointer.strip_prefix('/').unwrap_or(pointer);
+            if pointer.is_empty() { String::new() } else { format!("/{}", pointer) }
+        }).collect();
+        let mut results: Vec<AggResult> = aggs.iter().map(|(pointer, agg)| AggResult {
+            pointer: pointer.to_string(),
+            agg: *agg,
+            value: if matches!(agg, Agg::Count | Agg::DistinctCount) { Some(0.0) } else { None },
+            skipped: 0,
+        }).collect();
+        let mut distinct: Vec<HashSet<(bool, &str)>> = vec![HashSet::new(); aggs.len()];
+        JSONParser::for_each_array_element(input, prefix, options, |element| {
+            for entry in element.entries.iter() {
+                let Some((_, index_end)) = array_element_index(&entry.pointer.pointer, prefix) else {
+                    continue;
+                };
+                let column = &entry.pointer.pointer[index_end..];
+                let value_type = entry.pointer.value_type;
+                let number = match (value_type, entry.value) {
+                    (ValueType::Number, Some(value)) => value.parse::<f64>().ok(),
+                    _ => None,
+                };
+                for (i, result) in results.iter_mut().enumerate() {
+                    if columns[i] != column {
+                        continue;
+                    }
+                    match result.agg {
+                        Agg::Count | Agg::DistinctCount if matches!(value_type, ValueType::Null) => result.skipped += 1,
+                        Agg::Count => result.value = result.value.map(|count| count + 1.0),
+                        Agg::DistinctCount => {
+                            if distinct[i].insert((matches!(value_type, ValueType::String), entry.value.unwrap_or_default())) {
+                                result.value = result.value.map(|count| count + 1.0);
+                            }
+                        }
+                        Agg::Sum | Agg::Min | Agg::Max => match number {
+                            Some(number) => {
+                                result.value = Some(match (result.agg, result.value) {
+                                    (_, None) => number,
+                                    (Agg::Sum, Some(value)) => value + number,
+                                    (Agg::Min, Some(value)) => value.min(number),
+                                    (_, Some(value)) => value.max(number),
+                                });
+                            }
+                            None => result.skipped += 1,
+                        },
+                    }
+                }
+            }
+        })?;
+        Ok(AggResults { results })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::aggregate::{Agg, AggResult};
+    use crate::{JSONParser, ParseOptions};
+
+    #[test]
+    fn aggregate() {
+        let json = r#"{"items": [
+            {"price": 10.5, "qty": 2, "owner": {"country": "FR"}},
+            {"price": 4, "qty": null, "owner": {"country": "DE"}},
+            {"price": "n/a", "qty": 1, "owner": {"country": "FR"}},
+            {"qty": 3, "owner": {"country": null}}
+        ], "total": 1}"#;
+        let aggs = [("/price", Agg::Sum), ("price", Agg::Min), ("/price", Agg::Max), ("/qty", Agg::Count), ("/owner/country", Agg::DistinctCount), ("/missing", Agg::Sum)];
+        let res = JSONParser::aggregate(json, "/items", &aggs, ParseOptions::default()).unwrap();
+        let value = |pointer: &str, agg: Agg| res.get(pointer, agg).map(|result| (result.value, result.skipped));
+        assert_eq!(value("/price", Agg::Sum), Some((Some(14.5), 1)));
+        assert_eq!(value("price", Agg::Min), Some((Some(4.0), 1)));
+        assert_eq!(value("/price", Agg::Max), Some((Some(10.5), 1)));
+        assert_eq!(value("/qty", Agg::Count), Some((Some(3.0), 1)));
+        assert_eq!(value("/owner/country", Agg::DistinctCount), Some((Some(2.0), 1)));
+        assert_eq!(res.results[5], AggResult { pointer: "/missing".to_string(), agg: Agg::Sum, value: None, skipped: 0 });
+
+        let res = JSONParser::aggregate("[1, 2, 2, {\"a\": 1}, \"2\"]", "", &[("", Agg::DistinctCount), ("", Agg::Sum)], ParseOptions::default()).unwrap();
+        assert_eq!(res.results.iter().map(|result| (result.value, result.skipped)).collect::<Vec<_>>(), vec![(Some(4.0), 0), (Some(5.0), 2)]);
+    }
+}
@@ -0,0 +1,291 @@
+use std::fmt::Debug;
+
+use crate::error::ParseError;
+use crate::string_subtype::StringSubtype;
+use crate::{FlatJsonValue, GetBytes, ParseResult, ParseResultOwned, PointerKey, PointerRole, ValueType};
+
+const MAGIC: &[u8; 4] = b"JFPB";
+// to bump whenever the encoding of ParseResult or PointerKey changes, e.g. a new field
+const VERSION: u8 = 5;
+
+impl<V: Debug + Clone + AsRef<str> + GetBytes> ParseResult<V> {
+    /// Compact binary encoding of this result, e.g. to cache it across process restarts, read back with [`ParseResult::from_bytes`].
+    /// The format starts with a magic header and a version, integers are LEB128 varints and strings are length prefixed.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(16 + self.json.len() * 32);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        write_varint(&mut bytes, self.max_json_depth as u64);
+        bytes.push(self.parsing_max_depth);
+        bytes.push(self.depth_after_start_at);
+        write_optional_str(&mut bytes, self.started_parsing_at.as_deref());
+        write_optional_str(&mut bytes, self.parsing_prefix.as_deref());
+        write_varint(&mut bytes, self.source_span.start as u64);
+        write_varint(&mut bytes, self.source_span.end as u64);
+        write_varint(&mut bytes, self.json.len() as u64);
+        for entry in self.json.iter() {
+            let pointer = &entry.pointer;
+            write_str(&mut bytes, &pointer.pointer);
+            match pointer.value_type {
+                ValueType::Array(len) => {
+                    bytes.push(0);
+                    write_varint(&mut bytes, len as u64);
+                }
+                ValueType::Object(parsed) => bytes.push(if parsed { 1 } else { 2 }),
+                ValueType::Number => bytes.push(3),
+                ValueType::String => bytes.push(4),
+                ValueType::Bool => bytes.push(5),
+                ValueType::Null => bytes.push(6),
+                ValueType::None => bytes.push(7),
+            }
+            bytes.push(pointer.depth);
+            write_varint(&mut bytes, pointer.index as u64);
+            write_varint(&mut bytes, pointer.position as u64);
+            bytes.push(match pointer.string_subtype {
+                None => 0,
+                Some(StringSubtype::Uuid) => 1,
+                Some(StringSubtype::Email) => 2,
+                Some(StringSubtype::Url) => 3,
+            });
+            match pointer.key_span {
+                Some(ref key_span) => {
+                    bytes.push(1);
+                    write_varint(&mut bytes, key_span.start as u64);
+                    write_varint(&mut bytes, key_span.end as u64);
+                }
+                None => bytes.push(0),
+            }
+            match pointer.original_len {
+                Some(original_len) => {
+                    bytes.push(1);
+                    write_varint(&mut bytes, original_len as u64);
+                }
+                None => bytes.push(0),
+            }
+            bytes.push(match pointer.role {
+                None => 0,
+                Some(PointerRole::RootArray) => 1,
+                Some(PointerRole::RootObject) => 2,
+                Some(PointerRole::ArrayElement) => 3,
+                Some(PointerRole::ObjectMember) => 4,
+            });
+            match pointer.key_count {
+                Some(key_count) => {
+                    bytes.push(1);
+                    write_varint(&mut bytes, key_count as u64);
+                }
+                None => bytes.push(0),
+            }
+            write_optional_str(&mut bytes, entry.value.as_ref().map(|value| value.as_ref()));
+        }
+        bytes
+    }
+}
+
+impl ParseResultOwned {
+    /// Decode a result encoded with [`ParseResult::to_bytes`]. Fails with `ParseError::InvalidBinaryFormat` on a wrong magic header, an unsupported version
+    /// or invalid content, and with `ParseError::UnexpectedEof` on truncated input, offsets being in `bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<ParseResultOwned, ParseError> {
+        let mut reader = BinaryReader { bytes, index: 0 };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(ParseError::InvalidBinaryFormat { reason: "magic header", offset: 0 });
+        }
+        if reader.u8()? != VERSION {
+            return Err(ParseError::InvalidBinaryFormat { reason: "unsupported version", offset: MAGIC.len() });
+        }
+        let max_json_depth = reader.usize()?;
+        let parsing_max_depth = reader.u8()?;
+        let depth_after_start_at = reader.u8()?;
+        let started_parsing_at = reader.optional_string()?;
+        let parsing_prefix = reader.optional_string()?;
+        let source_span = reader.usize()?..reader.usize()?;
+        let len = reader.usize()?;
+        // every entry takes at least 11 bytes, don't trust len for the allocation
+        let mut json = Vec::with_capacity(len.min(bytes.len() / 11));
+        for _ in 0..len {
+            let pointer = reader.string()?;
+            let value_type_offset = reader.index;
+            let value_type = match reader.u8()? {
+                0 => ValueType::Array(reader.usize()?),
+                1 => ValueType::Object(true),
+                2 => ValueType::Object(false),
+                3 => ValueType::Number,
+                4 => ValueType::String,
+                5 => ValueType::Bool,
+                6 => ValueType::Null,
+                7 => ValueType::None,
+                _ => return Err(ParseError::InvalidBinaryFormat { reason: "value type", offset: value_type_offset }),
+            };
+            let depth = reader.u8()?;
+            let index = reader.usize()?;
+            let position = reader.usize()?;
+            let string_subtype_offset = reader.index;
+            let string_subtype = match reader.u8()? {
+                0 => None,
+                1 => Some(StringSubtype::Uuid),
+                2 => Some(StringSubtype::Email),
+                3 => Some(StringSubtype::Url),
+                _ => return Err(ParseError::InvalidBinaryFormat { reason: "string subtype", offset: string_subtype_offset }),
+            };
+            let key_span_offset = reader.index;
+            let key_span = match reader.u8()? {
+                0 => None,
+                1 => Some(reader.usize()?..reader.usize()?),
+                _ => return Err(ParseError::InvalidBinaryFormat { reason: "key span", offset: key_span_offset }),
+            };
+            let original_len_offset = reader.index;
+            let original_len = match reader.u8()? {
+                0 => None,
+                1 => Some(reader.usize()?),
+                _ => return Err(ParseError::InvalidBinaryFormat { reason: "original length", offset: original_len_offset }),
+            };
+            let role_offset = reader.index;
+            let role = match reader.u8()? {
+                0 => None,
+                1 => Some(PointerRole::RootArray),
+                2 => Some(PointerRole::RootObject),
+                3 => Some(PointerRole::ArrayElement),
+                4 => Some(PointerRole::ObjectMember),
+                _ => return Err(ParseError::InvalidBinaryFormat { reason: "role", offset: role_offset }),
+            };
+            let key_count_offset = reader.index;
+            let key_count = match reader.u8()? {
+                0 => None,
+                1 => Some(reader.usize()?),
+                _ => return Err(ParseError::InvalidBinaryFormat { reason: "key count", offset: key_count_offset }),
+            };
+            let value = reader.optional_string()?;
+            let mut pointer = PointerKey::from_pointer_and_index(pointer, value_type, depth, index, position);
+            pointer.string_subtype = string_subtype;
+            pointer.key_span = key_span;
+            pointer.original_len = original_len;
+            pointer.role = role;
+            pointer.key_count = key_count;
+            json.push(FlatJsonValue { pointer, value });
+        }
+        Ok(ParseResult { json, max_json_depth, parsing_max_depth, started_parsing_at, parsing_prefix, depth_after_start_at, source_span })
+    }
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn write_str(bytes: &mut Vec<u8>, value: &str) {
+    write_varint(bytes, value.len() as u64);
+    bytes.extend_from_slice(value.as_bytes());
+}
+
+fn write_optional_str(bytes: &mut Vec<u8>, value: Option<&str>) {
+    match value {
+        Some(value) => {
+            bytes.push(1);
+            write_str(bytes, value);
+        }
+        None => bytes.push(0),
+    }
+}
+
+struct BinaryReader<'a> {
+    bytes: &'a [u8],
+    index: usize,
+}
+
+impl<'a> BinaryReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], ParseError> {
+        if self.bytes.len() - self.index < len {
+            return Err(ParseError::UnexpectedEof { offset: self.bytes.len() });
+        }
+        self.index += len;
+        Ok(&self.bytes[self.index - len..self.index])
+    }
+
+    fn u8(&mut self) -> Result<u8, ParseError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn usize(&mut self) -> Result<usize, ParseError> {
+        let start = self.index;
+        let mut value: u64 = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return usize::try_from(value).map_err(|_| ParseError::InvalidBinaryFormat { reason: "integer overflow", offset: start });
+            }
+        }
+        Err(ParseError::InvalidBinaryFormat { reason: "integer overflow", offset: start })
+    }
+
+    fn string(&mut self) -> Result<String, ParseError> {
+        let len = self.usize()?;
+        let start = self.index;
+        let bytes = self.take(len)?;
+        std::str::from_utf8(bytes).map(str::to_string).map_err(|_| ParseError::InvalidBinaryFormat { reason: "invalid utf-8", offset: start })
+    }
+
+    fn optional_string(&mut self) -> Result<Option<String>, ParseError> {
+        let offset = self.index;
+        match self.u8()? {
+            0 => Ok(None),
+            1 => self.string().map(Some),
+            _ => Err(ParseError::InvalidBinaryFormat { reason: "optional string", offset }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::ParseError;
+    use crate::string_subtype::StringSubtype;
+    use crate::{JSONParser, ParseOptions, ParseResultOwned, PointerRole, ValueType};
+
+    #[test]
+    fn to_bytes_from_bytes() {
+        let json = r#"{"id": 1, "mail": "a@b.io", "skills": [{"name": "\"a\"", "levels": [1, 2]}, null], "deep": {"a": {"b": true}}, "empty": []}"#;
+        let res = JSONParser::parse(json, ParseOptions::default().max_depth(2).detect_string_subtypes(true).emit_keys(true).truncate_string_values(Some(5)).annotate_roles(true)).unwrap();
+        let bytes = res.to_bytes();
+        assert!(bytes.len() < json.len() * 4);
+        let decoded = ParseResultOwned::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.json.len(), res.json.len());
+        for (decoded, entry) in decoded.json.iter().zip(res.json.iter()) {
+            assert_eq!(decoded.pointer.pointer, entry.pointer.pointer);
+            assert_eq!(decoded.pointer.value_type, entry.pointer.value_type);
+            assert_eq!((decoded.pointer.depth, decoded.pointer.index, decoded.pointer.position), (entry.pointer.depth, entry.pointer.index, entry.pointer.position));
+            assert_eq!(decoded.pointer.string_subtype, entry.pointer.string_subtype);
+            assert_eq!(decoded.pointer.key_span, entry.pointer.key_span);
+            assert_eq!(decoded.pointer.original_len, entry.pointer.original_len);
+            assert_eq!(decoded.pointer.role, entry.pointer.role);
+            assert_eq!(decoded.pointer.key_count, entry.pointer.key_count);
+            assert_eq!(decoded.value.as_deref(), entry.value);
+        }
+        assert_eq!(decoded.json[1].pointer.string_subtype, Some(StringSubtype::Email));
+        assert_eq!(decoded.json[1].pointer.key_span, Some(11..15));
+        assert_eq!((decoded.json[1].value.as_deref(), decoded.json[1].pointer.original_len), (Some("a@b.i"), Some(6)));
+        assert_eq!(decoded.json[1].pointer.role, Some(PointerRole::ObjectMember));
+        assert_eq!(decoded.json.iter().find(|entry| entry.pointer.pointer == "/deep").unwrap().pointer.key_count, Some(1));
+        assert_eq!((decoded.max_json_depth, decoded.parsing_max_depth, decoded.source_span.clone()), (res.max_json_depth, res.parsing_max_depth, res.source_span.clone()));
+
+        let mut decoded = decoded;
+        JSONParser::change_depth_owned(&mut decoded, ParseOptions::default().max_depth(5)).unwrap();
+        assert!(decoded.json.iter().any(|entry| entry.pointer.pointer == "/deep/a/b" && entry.pointer.value_type == ValueType::Bool));
+    }
+
+    #[test]
+    fn from_bytes_errors() {
+        let res = JSONParser::parse(r#"{"a": [1, "b"]}"#, ParseOptions::default().prefix("/root".to_string())).unwrap();
+        let bytes = res.to_bytes();
+        assert_eq!(ParseResultOwned::from_bytes(&bytes).unwrap().parsing_prefix, Some("/root".to_string()));
+        for len in 0..bytes.len() {
+            assert!(ParseResultOwned::from_bytes(&bytes[0..len]).unwrap_err().is_unexpected_eof());
+        }
+        assert_eq!(ParseResultOwned::from_bytes(b"JFPA\x01").unwrap_err(), ParseError::InvalidBinaryFormat { reason: "magic header", offset: 0 });
+        let mut future_version = bytes.clone();
+        future_version[4] = 99;
+        assert_eq!(ParseResultOwned::from_bytes(&future_version).unwrap_err(), ParseError::InvalidBinaryFormat { reason: "unsupported version", offset: 4 });
+    }
+}
@@ -0,0 +1,139 @@
+use std::borrow::Cow;
+use std::fmt::Debug;
+use std::io;
+use std::io::Write;
+
+use crate::lexer::unescape;
+use crate::{concat_string, GetBytes, JsonArrayEntries, ValueType};
+
+/// Write array rows to CSV one at a time, so exporting a huge array does not require to keep all rows in memory.
+/// Columns are pointers relative to the row, e.g. `/name` or `/flags/a`, the empty pointer `""` selecting the row value itself.
+/// The header, made of the columns, is written on construction.
+pub struct CsvRowWriter<W: Write> {
+    writer: W,
+    columns: Vec<String>,
+    separator: u8,
+}
+
+impl<W: Write> CsvRowWriter<W> {
+    pub fn new(writer: W, columns: Vec<String>) -> io::Result<Self> {
+        Self::with_separator(writer, columns, b',')
+    }
+
+    pub fn with_separator(mut writer: W, columns: Vec<String>, separator: u8) -> io::Result<Self> {
+        for (i, column) in columns.iter().enumerate() {
+            if i > 0 {
+                writer.write_all(&[separator])?;
+            }
+            write_field(&mut writer, column.strip_prefix('/').unwrap_or(column), separator)?;
+        }
+        writer.write_all(b"\n")?;
+        Ok(Self { writer, columns, separator })
+    }
+
+    /// Write one line for `entries`, absent and null values are written as empty fields. String values are unescaped, e.g. `\"` as `"`, before being quoted for CSV.
+    pub fn write_row<V: Debug + Clone + AsRef<str> + GetBytes>(&mut self, entries: &JsonArrayEntries<V>) -> io::Result<()> {
+        let row_pointer = row_pointer(entries);
+        for (i, column) in self.columns.iter().enumerate() {
+            if i > 0 {
+                self.writer.write_all(&[self.separator])?;
+            }
+            let pointer = concat_string!(row_pointer, column);
+            let entry = entries.entries().iter().find(|entry| entry.pointer.pointer == pointer);
+            if let Some(entry) = entry {
+                if let Some(ref value) = entry.value {
+                    let value = if entry.pointer.value_type == ValueType::String { unescape(value.as_ref()) } else { Cow::Borrowed(value.as_ref()) };
+                    write_field(&mut self.writer, &value, self.separator)?;
+                }
+            }
+        }
+        self.writer.write_all(b"\n")
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+// pointer of the row value, the shallowest entry of the row or, when it is missing, the prefix of its entries ending with the row index
+fn row_pointer<V: Debug + Clone + AsRef<str> + GetBytes>(entries: &JsonArrayEntries<V>) -> &str {
+    let row_suffix = concat_string!("/", entries.index().to_string());
+    let Some(shallowest) = entries.entries().iter().min_by_key(|entry| entry.pointer.depth) else { return "" };
+    let pointer = shallowest.pointer.pointer.as_str();
+    if pointer.ends_with(row_suffix.as_str()) {
+        return pointer;
+    }
+    pointer.match_indices(row_suffix.as_str())
+        .map(|(i, _)| i + row_suffix.len())
+        .filter(|end| pointer[*end..].starts_with('/'))
+        .last()
+        .map_or("", |end| &pointer[..end])
+}
+
+fn write_field<W: Write>(writer: &mut W, field: &str, separator: u8) -> io::Result<()> {
+    if field.bytes().any(|b| b == separator || b == b'"' || b == b'\n' || b == b'\r') {
+        writer.write_all(b"\"")?;
+        writer.write_all(field.replace('"', "\"\"").as_bytes())?;
+        writer.write_all(b"\"")
+    } else {
+        writer.write_all(field.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::csv::CsvRowWriter;
+    use crate::{FlatJsonValue, JSONParser, JsonArrayEntries, ParseOptions};
+
+    #[test]
+    fn write_rows() {
+        let json = r#"{"skills": [{"id": 1, "name": "Bash", "flags": {"a": true}}, {"id": 2, "name": "Magnum, \"Break\""}, {"id": 3, "name": null}]}"#;
+        let res = JSONParser::parse(json, ParseOptions::default()).unwrap();
+        let mut writer = CsvRowWriter::new(Vec::new(), vec!["/id".to_string(), "/name".to_string(), "/flags/a".to_string()]).unwrap();
+        for index in 0..3 {
+            let prefix = format!("/skills/{}", index);
+            let entries: Vec<FlatJsonValue<&str>> = res.json.iter().filter(|e| e.pointer.pointer.starts_with(&prefix)).cloned().collect();
+            writer.write_row(&JsonArrayEntries { entries, index }).unwrap();
+        }
+        let csv = String::from_utf8(writer.into_inner()).unwrap();
+        assert_eq!(csv, "id,name,flags/a\n1,Bash,true\n2,\"Magnum, \"\"Break\"\"\",\n3,,\n");
+    }
+
+    #[test]
+    fn write_unescaped_strings() {
+        let res = JSONParser::parse(r#"[{"name": "caf\u00e9 \ud83d\ude00\ttab\\", "raw": {"a": "\""}}]"#, ParseOptions::default()).unwrap();
+        let mut writer = CsvRowWriter::new(Vec::new(), vec!["/name".to_string(), "/raw".to_string()]).unwrap();
+        writer.write_row(&JsonArrayEntries { entries: res.json[1..].to_vec(), index: 0 }).unwrap();
+        // raw data of containers is JSON, kept as written
+        assert_eq!(String::from_utf8(writer.into_inner()).unwrap(), "name,raw\ncafé 😀\ttab\\,\"{\"\"a\"\": \"\"\\\"\"\"\"}\"\n");
+    }
+
+    #[test]
+    fn write_scalar_rows() {
+        let res = JSONParser::parse("[10, 20]", ParseOptions::default()).unwrap();
+        let mut writer = CsvRowWriter::with_separator(Vec::new(), vec!["".to_string()], b';').unwrap();
+        for index in 0..2 {
+            let entries = vec![res.json[index + 1].clone()];
+            writer.write_row(&JsonArrayEntries { entries, index }).unwrap();
+        }
+        assert_eq!(String::from_utf8(writer.into_inner()).unwrap(), "\n10\n20\n");
+    }
+
+    #[test]
+    fn write_rows_exact_columns() {
+        let res = JSONParser::parse(r#"[{"b": {"a": {"id": 1}}}, {"a": {"id": 2}, "b": [{"a": {"id": 3}}]}]"#, ParseOptions::default()).unwrap();
+        let mut writer = CsvRowWriter::new(Vec::new(), vec!["/a/id".to_string()]).unwrap();
+        for index in 0..2 {
+            let prefix = format!("/{}", index);
+            let entries: Vec<FlatJsonValue<&str>> = res.json.iter().filter(|e| e.pointer.pointer.starts_with(&prefix)).cloned().collect();
+            writer.write_row(&JsonArrayEntries { entries, index }).unwrap();
+        }
+        let entries: Vec<FlatJsonValue<&str>> = res.json.iter().filter(|e| e.pointer.pointer.starts_with("/1/")).cloned().collect();
+        writer.write_row(&JsonArrayEntries { entries, index: 1 }).unwrap();
+        assert_eq!(String::from_utf8(writer.into_inner()).unwrap(), "a/id\n\n2\n2\n");
+    }
+}
@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Write};
+
+use crate::{GetBytes, ParseResult};
+
+const MAX_LABEL_VALUE_LEN: usize = 32;
+
+impl<V: Debug + Clone + AsRef<str> + GetBytes> ParseResult<V> {
+    /// Graphviz DOT rendering of the document tree, for debugging: a node per entry labeled with its pointer, value type and value truncated to 32 chars,
+    /// and an edge from its parent. Entries whose parent has no entry, e.g. children of the root object, are attached to the `root` node.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::with_capacity(64 + self.json.len() * 64);
+        dot.push_str("digraph json {\n    node [shape=box];\n");
+        let root = self.json.iter().find(|entry| entry.pointer.pointer.is_empty());
+        let root_label = root.map_or("/".to_string(), |entry| format!("/\\n{:?}", entry.pointer.value_type));
+        let _ = writeln!(dot, "    root [label=\"{}\"];", root_label);
+        let nodes: HashMap<&str, usize> = self.json.iter().enumerate().map(|(i, entry)| (entry.pointer.pointer.as_str(), i)).collect();
+        for (i, entry) in self.json.iter().enumerate() {
+            if entry.pointer.pointer.is_empty() {
+                continue;
+            }
+            let _ = write!(dot, "    n{} [label=\"{}\\n{:?}", i, escape(&entry.pointer.pointer), entry.pointer.value_type);
+            if let Some(ref value) = entry.value {
+                let value = value.as_ref();
+                match value.char_indices().nth(MAX_LABEL_VALUE_LEN) {
+                    Some((end, _)) => { let _ = write!(dot, "\\n{}...", escape(&value[0..end])); }
+                    None => { let _ = write!(dot, "\\n{}", escape(value)); }
+                }
+            }
+            dot.push_str("\"];\n");
+            match nodes.get(entry.pointer.parent()) {
+                Some(parent) => { let _ = writeln!(dot, "    n{} -> n{};", parent, i); }
+                None => { let _ = writeln!(dot, "    root -> n{};", i); }
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{JSONParser, ParseOptions};
+
+    #[test]
+    fn to_dot() {
+        let json = r#"{"name": "say \"hi\"", "tags": ["a"], "long": "0123456789012345678901234567890123456789"}"#;
+        let res = JSONParser::parse(json, ParseOptions::default()).unwrap();
+        assert_eq!(res.to_dot(), r#"digraph json {
+    node [shape=box];
+    root [label="/"];
+    n0 [label="/name\nString\nsay \\\"hi\\\""];
+    root -> n0;
+    n1 [label="/tags\nArray(1)"];
+    root -> n1;
+    n2 [label="/tags/0\nString\na"];
+    n1 -> n2;
+    n3 [label="/long\nString\n01234567890123456789012345678901..."];
+    root -> n3;
+}
+"#);
+
+        let res = JSONParser::parse("[1]", ParseOptions::default()).unwrap();
+        assert_eq!(res.to_dot(), "digraph json {\n    node [shape=box];\n    root [label=\"/\\nArray(1)\"];\n    n1 [label=\"/0\\nNumber\\n1\"];\n    root -> n1;\n}\n");
+    }
+}
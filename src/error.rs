@@ -0,0 +1,268 @@
+use std::fmt::{Display, Formatter};
+use std::io::ErrorKind;
+
+/// Error of a parse, offsets are byte offsets in the parsed input where the error was detected. Every variant has one, see [`ParseError::offset`],
+/// `InvalidOptions` being at offset 0 as options are checked before reading input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// A token which is not allowed at this location, e.g. a value where a key is expected, or a document not starting with `{` or `[`.
+    UnexpectedToken { expected: &'static str, found: String, offset: usize },
+    /// Input ended before the document, e.g. a truncated chunk: more bytes are needed. Empty input is reported as this error at offset 0.
+    UnexpectedEof { offset: usize },
+    /// Nesting is deeper than the maximum depth which can be represented, `u8::MAX`.
+    DepthExceeded { offset: usize },
+    InvalidNumber { offset: usize },
+    /// A number longer than `ParseOptions::max_number_length`.
+    NumberTooLong { offset: usize },
+    /// An escape sequence not allowed by JSON, offset being the one of its backslash. Only with `ParseOptions::deny_unknown_escape`.
+    InvalidEscape { offset: usize },
+    /// Bytes which are not UTF-8 in a string or in raw data of a container, given to `parse_bytes` or `validate_stream`, offset being the one of the first byte of the invalid sequence.
+    InvalidUtf8 { offset: usize },
+    /// A limit set on parse options has been reached, `limit` being the option name.
+    LimitExceeded { limit: &'static str, offset: usize },
+    /// Bytes given to `ParseResultOwned::from_bytes` are not a result encoded by `to_bytes`, `reason` being the invalid part.
+    InvalidBinaryFormat { reason: &'static str, offset: usize },
+    /// Reading the input failed, e.g. when reading frames with `FramedJsonReader`.
+    Io { kind: ErrorKind, offset: usize },
+    /// Contradictory or meaningless options, see `ParseOptions::validate`. Offset is always 0, input being not read.
+    InvalidOptions { reason: String, offset: usize },
+}
+
+impl ParseError {
+    pub fn is_unexpected_eof(&self) -> bool {
+        matches!(self, ParseError::UnexpectedEof { .. })
+    }
+
+    pub fn is_depth_exceeded(&self) -> bool {
+        matches!(self, ParseError::DepthExceeded { .. })
+    }
+
+    pub fn is_invalid_number(&self) -> bool {
+        matches!(self, ParseError::InvalidNumber { .. })
+    }
+
+    pub fn is_number_too_long(&self) -> bool {
+        matches!(self, ParseError::NumberTooLong { .. })
+    }
+
+    pub fn is_invalid_escape(&self) -> bool {
+        matches!(self, ParseError::InvalidEscape { .. })
+    }
+
+    pub fn is_invalid_utf8(&self) -> bool {
+        matches!(self, ParseError::InvalidUtf8 { .. })
+    }
+
+    pub fn is_limit_exceeded(&self) -> bool {
+        matches!(self, ParseError::LimitExceeded { .. })
+    }
+
+    pub fn is_invalid_binary_format(&self) -> bool {
+        matches!(self, ParseError::InvalidBinaryFormat { .. })
+    }
+
+    pub fn is_invalid_options(&self) -> bool {
+        matches!(self, ParseError::InvalidOptions { .. })
+    }
+
+    pub fn offset(&self) -> usize {
+        match self {
+            ParseError::UnexpectedToken { offset, .. }
+            | ParseError::UnexpectedEof { offset }
+            | ParseError::DepthExceeded { offset }
+            | ParseError::InvalidNumber { offset }
+            | ParseError::NumberTooLong { offset }
+            | ParseError::InvalidEscape { offset }
+            | ParseError::InvalidUtf8 { offset }
+            | ParseError::LimitExceeded { offset, .. }
+            | ParseError::InvalidBinaryFormat { offset, .. }
+            | ParseError::Io { offset, .. }
+            | ParseError::InvalidOptions { offset, .. } => *offset,
+        }
+    }
+}
+
+impl ParseError {
+    // the same error, its offset being moved by `delta`, e.g. from a slice of the input to the whole input
+    pub(crate) fn offset_by(mut self, delta: usize) -> Self {
+        match self {
+            ParseError::UnexpectedToken { ref mut offset, .. }
+            | ParseError::UnexpectedEof { ref mut offset }
+            | ParseError::DepthExceeded { ref mut offset }
+            | ParseError::InvalidNumber { ref mut offset }
+            | ParseError::NumberTooLong { ref mut offset }
+            | ParseError::InvalidEscape { ref mut offset }
+            | ParseError::InvalidUtf8 { ref mut offset }
+            | ParseError::LimitExceeded { ref mut offset, .. }
+            | ParseError::InvalidBinaryFormat { ref mut offset, .. }
+            | ParseError::Io { ref mut offset, .. } => *offset += delta,
+            // before any input, whatever the slice
+            ParseError::InvalidOptions { .. } => {}
+        }
+        self
+    }
+
+    /// Multi-line rendering of this error against `source`, the parsed input: the line of the offset with a caret under its column, like compiler errors.
+    /// Long lines, e.g. minified documents, are cut around the offset. Lines and columns start at 1, columns counting characters. `InvalidOptions` is rendered alone, `source` being not read.
+    pub fn render(&self, source: &str) -> String {
+        const CONTEXT: usize = 40;
+        if self.is_invalid_options() {
+            return format!("error: {}", self);
+        }
+        let mut offset = self.offset().min(source.len());
+        while !source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        let line_start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[offset..].find('\n').map_or(source.len(), |i| offset + i);
+        let line_number = source[..line_start].matches('\n').count() + 1;
+        let before: Vec<char> = source[line_start..offset].chars().collect();
+        let after: Vec<char> = source[offset..line_end].trim_end_matches('\r').chars().collect();
+        let column = before.len() + 1;
+        let (prefix, before) = if before.len() > CONTEXT { ("...", &before[before.len() - CONTEXT..]) } else { ("", &before[..]) };
+        let (suffix, after) = if after.len() > CONTEXT { ("...", &after[..CONTEXT]) } else { ("", &after[..]) };
+        let before: String = before.iter().collect();
+        let after: String = after.iter().collect();
+        let gutter = " ".repeat(line_number.to_string().len());
+        format!("error: {}\n{} --> line {}, column {}\n{} |\n{} | {}{}{}{}\n{} | {}^",
+                self, gutter, line_number, column, gutter, line_number, prefix, before, after, suffix, gutter, " ".repeat(prefix.len() + before.chars().count()))
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::UnexpectedToken { expected, found, offset } => write!(f, "Expected {} but found {} at offset {}", expected, found, offset),
+            ParseError::UnexpectedEof { offset } => write!(f, "Unexpected end of input at offset {}", offset),
+            ParseError::DepthExceeded { offset } => write!(f, "Maximum nesting depth of {} exceeded at offset {}", u8::MAX, offset),
+            ParseError::InvalidNumber { offset } => write!(f, "Invalid number at offset {}", offset),
+            ParseError::NumberTooLong { offset } => write!(f, "Number too long at offset {}", offset),
+            ParseError::InvalidEscape { offset } => write!(f, "Invalid escape sequence at offset {}", offset),
+            ParseError::InvalidUtf8 { offset } => write!(f, "Invalid UTF-8 at offset {}", offset),
+            ParseError::LimitExceeded { limit, offset } => write!(f, "Limit {} exceeded at offset {}", limit, offset),
+            ParseError::InvalidBinaryFormat { reason, offset } => write!(f, "Invalid binary format, {} at offset {}", reason, offset),
+            ParseError::Io { kind, offset } => write!(f, "Read error {} at offset {}", kind, offset),
+            ParseError::InvalidOptions { reason, .. } => write!(f, "Invalid options, {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// A value read with `ParseResult::try_get` which cannot be parsed as the requested type, `expected` being its name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeMismatch {
+    pub pointer: String,
+    pub expected: &'static str,
+}
+
+impl Display for TypeMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Expected {} at {}", self.expected, self.pointer)
+    }
+}
+
+impl std::error::Error for TypeMismatch {}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use crate::error::ParseError;
+    use crate::{JSONParser, ParseOptions, ParseResultOwned};
+
+    #[test]
+    fn error_predicates() {
+        let err = JSONParser::parse(r#"{"a": [1, 2"#, ParseOptions::default()).unwrap_err();
+        assert!(err.is_unexpected_eof());
+        assert_eq!(err.offset(), 11);
+        assert!(JSONParser::parse("  ", ParseOptions::default()).unwrap_err().is_unexpected_eof());
+        assert!(!JSONParser::parse(r#"{"a" 1}"#, ParseOptions::default()).unwrap_err().is_unexpected_eof());
+
+        let err = JSONParser::parse(r#"{"a": 1.2.3}"#, ParseOptions::default()).unwrap_err();
+        assert!(err.is_invalid_number());
+        assert_eq!(err.offset(), 6);
+
+        let nested = "[".repeat(300) + &"]".repeat(300);
+        let err = JSONParser::parse(&nested, ParseOptions::default().max_depth(u8::MAX)).unwrap_err();
+        assert_eq!(err.offset(), 255);
+        assert!(err.is_depth_exceeded());
+        assert!(!err.is_limit_exceeded());
+    }
+
+    #[test]
+    fn render() {
+        let source = "{\n  \"a\": 1,\n  \"b\" 2\n}";
+        let err = JSONParser::parse(source, ParseOptions::default()).unwrap_err();
+        assert_eq!(err.render(source), "error: Expected ':' after object key but found Number(\"2\") at offset 18\n  --> line 3, column 7\n  |\n3 |   \"b\" 2\n  |       ^");
+
+        let source = format!("{{{}\"é\": 1.2.3}}", r#""k": 1, "#.repeat(20));
+        let err = JSONParser::parse(&source, ParseOptions::default()).unwrap_err();
+        let rendered = err.render(&source);
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(lines[1], "  --> line 1, column 167");
+        assert_eq!(lines[3], r#"1 | ...1, "k": 1, "k": 1, "k": 1, "k": 1, "é": 1.2.3}"#);
+        assert_eq!(lines[4].find('^'), Some(4 + 3 + 40));
+    }
+
+    #[test]
+    fn every_variant_offset() {
+        // no wildcard: a new variant has to be produced below
+        fn variant(err: &ParseError) -> &'static str {
+            match err {
+                ParseError::UnexpectedToken { .. } => "UnexpectedToken",
+                ParseError::UnexpectedEof { .. } => "UnexpectedEof",
+                ParseError::DepthExceeded { .. } => "DepthExceeded",
+                ParseError::InvalidNumber { .. } => "InvalidNumber",
+                ParseError::NumberTooLong { .. } => "NumberTooLong",
+                ParseError::InvalidEscape { .. } => "InvalidEscape",
+                ParseError::InvalidUtf8 { .. } => "InvalidUtf8",
+                ParseError::LimitExceeded { .. } => "LimitExceeded",
+                ParseError::InvalidBinaryFormat { .. } => "InvalidBinaryFormat",
+                ParseError::Io { .. } => "Io",
+                ParseError::InvalidOptions { .. } => "InvalidOptions",
+            }
+        }
+        struct Failing;
+        impl Read for Failing {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::ErrorKind::BrokenPipe.into())
+            }
+        }
+        let nested = "[".repeat(300);
+        let errors = vec![
+            (JSONParser::parse(r#"{"a" 1}"#, ParseOptions::default()).unwrap_err(), 5),
+            (JSONParser::parse("[1, 2", ParseOptions::default()).unwrap_err(), 5),
+            (JSONParser::parse(&nested, ParseOptions::default().max_depth(u8::MAX)).unwrap_err(), 255),
+            (JSONParser::parse(r#"{"a": 1.2.3}"#, ParseOptions::default()).unwrap_err(), 6),
+            (JSONParser::parse(r#"{"a": 12345}"#, ParseOptions::default().max_number_length(3)).unwrap_err(), 6),
+            (JSONParser::parse(r#"{"a": "\q"}"#, ParseOptions::default()).unwrap_err(), 7),
+            (JSONParser::parse_bytes(b"{\"a\": \"b\xff\"}", ParseOptions::default()).unwrap_err(), 8),
+            (JSONParser::parse("[1, 2, 3]", ParseOptions::default().max_steps(2)).unwrap_err(), 7),
+            (ParseResultOwned::from_bytes(b"JFPA").unwrap_err(), 0),
+            (JSONParser::validate_stream(Failing).unwrap_err().0, 0),
+            // options are checked before reading input
+            (JSONParser::parse("[]", ParseOptions::default().array_index_base(2)).unwrap_err(), 0),
+        ];
+        for (err, offset) in errors.iter() {
+            assert_eq!(err.offset(), *offset, "{:?}", err);
+            assert!(err.render("").starts_with("error: "));
+        }
+        let mut variants: Vec<&str> = errors.iter().map(|(err, _)| variant(err)).collect();
+        variants.sort();
+        variants.dedup();
+        assert_eq!(variants.len(), errors.len());
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let input = b"{\"a\": [1, \"\xe9t\xe9\"]}";
+        let err = JSONParser::parse_bytes(input, ParseOptions::default()).unwrap_err();
+        assert_eq!(err, ParseError::InvalidUtf8 { offset: 11 });
+        assert!(err.is_invalid_utf8());
+        assert_eq!(JSONParser::validate_stream(&input[..]).unwrap_err().0, ParseError::InvalidUtf8 { offset: 11 });
+        // tolerated missing '}' would otherwise accept the truncated input
+        assert!(JSONParser::parse_bytes(b"{\"a\": \"\xff\"", ParseOptions::default()).unwrap_err().is_invalid_utf8());
+        assert_eq!(JSONParser::validate_stream(&b"[\"\xe2\x82x\"]"[..]).unwrap_err().0, ParseError::InvalidUtf8 { offset: 2 });
+        assert!(JSONParser::parse_bytes(b"{\"a\": {\"b\": \"\xff\"}}", ParseOptions::default().max_depth(1)).unwrap_err().is_invalid_utf8());
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+
+use crate::{FlatJsonValue, GetBytes, ParseResult, ValueType};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JsonEvent<'a> {
+    StartObject,
+    Key(&'a str),
+    StartArray,
+    /// A scalar, or a container which was not expanded (e.g. beyond `max_depth`) along with its raw data when kept.
+    Value(ValueType, Option<&'a str>),
+    EndArray,
+    EndObject,
+}
+
+#[derive(Clone, Copy)]
+enum Node<'a> {
+    Entry(usize),
+    // parent of an entry without its own container entry, e.g. added manually or skipped by `start_parse_at`, like the serializer it is an object
+    Implied(&'a str),
+}
+
+struct EventsBuilder<'a, V: Debug + Clone + AsRef<str> + GetBytes> {
+    json: &'a [FlatJsonValue<V>],
+    pointers: HashSet<&'a str>,
+    children: HashMap<&'a str, Vec<Node<'a>>>,
+    events: Vec<JsonEvent<'a>>,
+}
+
+impl<V: Debug + Clone + AsRef<str> + GetBytes> ParseResult<V> {
+    /// Structural events of the document, in document order, reconstructed from flat entries: the inverse of flattening, for event-oriented consumers like templating engines.
+    /// The root is an array when there is an array entry with the empty pointer, an object otherwise. Siblings are ordered by `position`.
+    /// Like for the serializer, entries typed `None` are absent values and are skipped.
+    pub fn as_events(&self) -> impl Iterator<Item=JsonEvent<'_>> {
+        let pointers = self.json.iter().filter(|entry| !matches!(entry.pointer.value_type, ValueType::None)).map(|entry| entry.pointer.pointer.as_str()).collect();
+        let mut builder = EventsBuilder { json: &self.json, pointers, children: HashMap::with_capacity(self.json.len() / 2), events: Vec::with_capacity(self.json.len() * 2) };
+        let mut root_is_array = false;
+        for (i, entry) in self.json.iter().enumerate() {
+            if matches!(entry.pointer.value_type, ValueType::None) {
+                continue;
+            }
+            if entry.pointer.pointer.is_empty() {
+                root_is_array = matches!(entry.pointer.value_type, ValueType::Array(_));
+                continue;
+            }
+            builder.add_child(parent_pointer(&entry.pointer.pointer), Node::Entry(i));
+        }
+        for children in builder.children.values_mut() {
+            children.sort_by_key(|node| match node {
+                Node::Entry(i) => self.json[*i].pointer.position,
+                Node::Implied(_) => usize::MAX,
+            });
+        }
+        builder.emit_container("", root_is_array);
+        builder.events.into_iter()
+    }
+}
+
+impl<'a, V: Debug + Clone + AsRef<str> + GetBytes> EventsBuilder<'a, V> {
+    fn add_child(&mut self, parent: &'a str, node: Node<'a>) {
+        let parent_is_known = parent.is_empty() || self.pointers.contains(parent) || self.children.contains_key(parent);
+        if !parent_is_known {
+            self.add_child(parent_pointer(parent), Node::Implied(parent));
+        }
+        self.children.entry(parent).or_default().push(node);
+    }
+
+    fn emit_container(&mut self, pointer: &'a str, is_array: bool) {
+        self.events.push(if is_array { JsonEvent::StartArray } else { JsonEvent::StartObject });
+        let children = self.children.remove(pointer).unwrap_or_default();
+        for node in children {
+            let child_pointer = match node {
+                Node::Entry(i) => self.json[i].pointer.pointer.as_str(),
+                Node::Implied(pointer) => pointer,
+            };
+            if !is_array {
+                self.events.push(JsonEvent::Key(&child_pointer[child_pointer.rfind('/').map_or(0, |i| i + 1)..]));
+            }
+            match node {
+                Node::Implied(pointer) => self.emit_container(pointer, false),
+                Node::Entry(i) => {
+                    let entry = &self.json[i];
+                    let has_children = self.children.contains_key(child_pointer);
+                    match entry.pointer.value_type {
+                        ValueType::Object(parsed) if parsed || has_children => self.emit_container(child_pointer, false),
+                        ValueType::Array(_) if entry.value.is_none() || has_children => self.emit_container(child_pointer, true),
+                        value_type => self.events.push(JsonEvent::Value(value_type, entry.value.as_ref().map(|v| v.as_ref()))),
+                    }
+                }
+            }
+        }
+        self.events.push(if is_array { JsonEvent::EndArray } else { JsonEvent::EndObject });
+    }
+}
+
+fn parent_pointer(pointer: &str) -> &str {
+    &pointer[0..pointer.rfind('/').unwrap_or(0)]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::events::JsonEvent::*;
+    use crate::{FlatJsonValue, JSONParser, ParseOptions, PointerKey, ValueType};
+
+    #[test]
+    fn object_events() {
+        let json = r#"{"a": 1, "b": [true, {"c": null}], "d": {}, "e": []}"#;
+        let res = JSONParser::parse(json, ParseOptions::default()).unwrap();
+        let events: Vec<_> = res.as_events().collect();
+        assert_eq!(events, vec![StartObject,
+                                Key("a"), Value(ValueType::Number, Some("1")),
+                                Key("b"), StartArray, Value(ValueType::Bool, Some("true")), StartObject, Key("c"), Value(ValueType::Null, None), EndObject, EndArray,
+                                Key("d"), StartObject, EndObject,
+                                Key("e"), StartArray, EndArray,
+                                EndObject]);
+    }
+
+    #[test]
+    fn array_events() {
+        let res = JSONParser::parse(r#"[[1, 2], {"a": "b"}]"#, ParseOptions::default()).unwrap();
+        let events: Vec<_> = res.as_events().collect();
+        assert_eq!(events, vec![StartArray, StartArray, Value(ValueType::Number, Some("1")), Value(ValueType::Number, Some("2")), EndArray,
+                                StartObject, Key("a"), Value(ValueType::String, Some("b")), EndObject, EndArray]);
+    }
+
+    #[test]
+    fn events_after_change_depth() {
+        let json = r#"{"a": {"b": {"c": 1}}, "d": [{"e": 2}]}"#;
+        let mut res = JSONParser::parse(json, ParseOptions::default().max_depth(1)).unwrap();
+        let events: Vec<_> = res.as_events().collect();
+        assert_eq!(events, vec![StartObject, Key("a"), Value(ValueType::Object(false), Some(r#"{"b": {"c": 1}}"#)), Key("d"), Value(ValueType::Array(1), Some(r#"[{"e": 2}]"#)), EndObject]);
+        JSONParser::change_depth(&mut res, ParseOptions::default().max_depth(10)).unwrap();
+        let events: Vec<_> = res.as_events().collect();
+        assert_eq!(events, vec![StartObject, Key("a"), StartObject, Key("b"), StartObject, Key("c"), Value(ValueType::Number, Some("1")), EndObject, EndObject,
+                                Key("d"), StartArray, StartObject, Key("e"), Value(ValueType::Number, Some("2")), EndObject, EndArray, EndObject]);
+    }
+
+    #[test]
+    fn events_with_missing_parent() {
+        let mut res = JSONParser::parse(r#"{"a": 1}"#, ParseOptions::default()).unwrap();
+        res.json.push(FlatJsonValue { pointer: PointerKey::from_pointer("/b/c".to_string(), ValueType::Bool, 2, 2), value: Some("true") });
+        res.json.push(FlatJsonValue { pointer: PointerKey::from_pointer("/d".to_string(), ValueType::None, 1, 3), value: None });
+        let events: Vec<_> = res.as_events().collect();
+        assert_eq!(events, vec![StartObject, Key("a"), Value(ValueType::Number, Some("1")), Key("b"), StartObject, Key("c"), Value(ValueType::Bool, Some("true")), EndObject, EndObject]);
+    }
+}
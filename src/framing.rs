@@ -0,0 +1,168 @@
+use std::io::{ErrorKind, Read};
+
+use crate::error::ParseError;
+use crate::{JSONParser, ParseOptions, ParseResultOwned};
+
+/// Default of [`FramedJsonReader::max_frame_len`]: 16 MiB.
+pub const DEFAULT_MAX_FRAME_LEN: usize = 16 * 1024 * 1024;
+
+/// Read length prefixed JSON messages, e.g. RPC frames from a socket: each frame is a big-endian u32 length followed by that many bytes holding one JSON document.
+/// Iterating yields one parsed frame at a time and ends on a clean end of input between frames.
+/// A frame truncated by the end of input is an `UnexpectedEof` error, offsets of read errors being relative to the start of the stream
+/// while offsets of parse errors are relative to the frame payload. Iteration stops after a read error, not after a parse error.
+pub struct FramedJsonReader<R: Read> {
+    reader: R,
+    options: ParseOptions,
+    max_frame_len: usize,
+    buffer: Vec<u8>,
+    offset: usize,
+    done: bool,
+}
+
+impl<R: Read> FramedJsonReader<R> {
+    pub fn new(reader: R, options: ParseOptions) -> Self {
+        Self { reader, options, max_frame_len: DEFAULT_MAX_FRAME_LEN, buffer: Vec::new(), offset: 0, done: false }
+    }
+
+    /// Frames announcing a larger length fail with `LimitExceeded { limit: "max_frame_len", .. }` before their payload is read, and stop iteration.
+    /// Defaults to [`DEFAULT_MAX_FRAME_LEN`]. The payload buffer grows as bytes are read, so a length prefix alone never allocates the whole announced length.
+    pub fn max_frame_len(mut self, max_frame_len: usize) -> Self {
+        self.max_frame_len = max_frame_len;
+        self
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    // outer error is a framing or read error after which the stream can't be read anymore, inner one is a parse error of the frame payload
+    fn read_frame(&mut self) -> Result<Option<Result<ParseResultOwned, ParseError>>, ParseError> {
+        let mut prefix = [0_u8; 4];
+        let read = self.read_fully(&mut prefix)?;
+        if read == 0 {
+            return Ok(None);
+        }
+        if read < prefix.len() {
+            return Err(ParseError::UnexpectedEof { offset: self.offset });
+        }
+        let frame_start = self.offset - prefix.len();
+        let len = u32::from_be_bytes(prefix) as usize;
+        if len > self.max_frame_len {
+            return Err(ParseError::LimitExceeded { limit: "max_frame_len", offset: frame_start });
+        }
+        let mut buffer = std::mem::take(&mut self.buffer);
+        buffer.clear();
+        let read = (&mut self.reader).take(len as u64).read_to_end(&mut buffer);
+        // bytes read before an error are appended to the buffer too
+        self.offset += buffer.len();
+        let res = match read {
+            Ok(read) if read < len => Err(ParseError::UnexpectedEof { offset: self.offset }),
+            Ok(_) => Ok(Some(JSONParser::parse_bytes(&buffer, self.options.clone()).map(|res| res.to_owned()))),
+            Err(e) => Err(ParseError::Io { kind: e.kind(), offset: self.offset }),
+        };
+        self.buffer = buffer;
+        res
+    }
+
+    // read until `buf` is full or end of input, returning the number of bytes read
+    fn read_fully(&mut self, buf: &mut [u8]) -> Result<usize, ParseError> {
+        let mut read = 0;
+        while read < buf.len() {
+            match self.reader.read(&mut buf[read..]) {
+                Ok(0) => break,
+                Ok(n) => {
+                    read += n;
+                    self.offset += n;
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(ParseError::Io { kind: e.kind(), offset: self.offset }),
+            }
+        }
+        Ok(read)
+    }
+}
+
+impl<R: Read> Iterator for FramedJsonReader<R> {
+    type Item = Result<ParseResultOwned, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.read_frame() {
+            Ok(Some(res)) => Some(res),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, ErrorKind, Read};
+
+    use crate::error::ParseError;
+    use crate::framing::FramedJsonReader;
+    use crate::ParseOptions;
+
+    fn frame(json: &str) -> Vec<u8> {
+        let mut frame = (json.len() as u32).to_be_bytes().to_vec();
+        frame.extend_from_slice(json.as_bytes());
+        frame
+    }
+
+    #[test]
+    fn read_frames() {
+        let mut stream = frame(r#"{"id": 1}"#);
+        stream.extend(frame(r#"{"id": "#));
+        stream.extend(frame(r#"[true, false]"#));
+        let mut reader = FramedJsonReader::new(Cursor::new(stream), ParseOptions::default());
+        let res = reader.next().unwrap().unwrap();
+        assert_eq!(res.json[0].pointer.pointer, "/id");
+        assert_eq!(res.json[0].value.as_deref(), Some("1"));
+        assert!(reader.next().unwrap().unwrap_err().is_unexpected_eof());
+        assert_eq!(reader.next().unwrap().unwrap().json.len(), 3);
+        assert!(reader.next().is_none());
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn truncated_frames() {
+        let stream = frame(r#"{"id": 1}"#);
+        let mut reader = FramedJsonReader::new(Cursor::new(stream[0..8].to_vec()), ParseOptions::default());
+        assert_eq!(reader.next().unwrap().unwrap_err(), ParseError::UnexpectedEof { offset: 8 });
+        assert!(reader.next().is_none());
+
+        let mut reader = FramedJsonReader::new(Cursor::new(vec![0, 0]), ParseOptions::default());
+        assert_eq!(reader.next().unwrap().unwrap_err(), ParseError::UnexpectedEof { offset: 2 });
+
+        let mut reader = FramedJsonReader::new(Cursor::new(frame("[1, 2, 3]")), ParseOptions::default()).max_frame_len(4);
+        assert_eq!(reader.next().unwrap().unwrap_err(), ParseError::LimitExceeded { limit: "max_frame_len", offset: 0 });
+        assert!(reader.next().is_none());
+
+        let mut reader = FramedJsonReader::new(Cursor::new(vec![0xff, 0xff, 0xff, 0xff, b'[']), ParseOptions::default());
+        assert_eq!(reader.next().unwrap().unwrap_err(), ParseError::LimitExceeded { limit: "max_frame_len", offset: 0 });
+        // 1 MiB announced, only one byte of payload
+        let mut reader = FramedJsonReader::new(Cursor::new(vec![0, 0x10, 0, 0, b'[']), ParseOptions::default());
+        assert_eq!(reader.next().unwrap().unwrap_err(), ParseError::UnexpectedEof { offset: 5 });
+    }
+
+    #[test]
+    fn read_error() {
+        struct Failing;
+        impl Read for Failing {
+            fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+                Err(ErrorKind::ConnectionReset.into())
+            }
+        }
+        let mut reader = FramedJsonReader::new(Failing, ParseOptions::default());
+        assert_eq!(reader.next().unwrap().unwrap_err(), ParseError::Io { kind: ErrorKind::ConnectionReset, offset: 0 });
+        assert!(reader.next().is_none());
+    }
+}
@@ -0,0 +1,142 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::error::ParseError;
+use crate::{owned_value, JSONParser, ParseOptions, PointerKey};
+
+/// Pointers shared by results of [`JSONParser::parse_owned_with_pool`], so that documents of the same schema store their keys once, and values with [`ParseOptions::intern_values`].
+/// Whole pointers are interned rather than each key segment: a pointer shares its segments with every document, and an `Arc<str>` per segment would take more room than most keys.
+#[derive(Debug, Default)]
+pub struct KeyPool {
+    pointers: HashSet<Arc<str>>,
+    values: HashSet<Arc<str>>,
+}
+
+impl KeyPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn intern(&mut self, pointer: &str) -> Arc<str> {
+        if let Some(interned) = self.pointers.get(pointer) {
+            return interned.clone();
+        }
+        let interned: Arc<str> = Arc::from(pointer);
+        self.pointers.insert(interned.clone());
+        interned
+    }
+
+    pub fn len(&self) -> usize {
+        self.pointers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pointers.is_empty()
+    }
+
+    pub fn intern_value(&mut self, value: &str) -> Arc<str> {
+        if let Some(interned) = self.values.get(value) {
+            return interned.clone();
+        }
+        let interned: Arc<str> = Arc::from(value);
+        self.values.insert(interned.clone());
+        interned
+    }
+
+    /// Distinct values interned with `intern_values`.
+    pub fn value_count(&self) -> usize {
+        self.values.len()
+    }
+}
+
+/// Entry of [`PooledParseResult`]: `key` holds every field of the parsed entry but its pointer, which is left empty and shared through the pool as `pointer`.
+#[derive(Debug, Clone)]
+pub struct PooledEntry {
+    pub pointer: Arc<str>,
+    pub key: PointerKey,
+    pub value: Option<Arc<str>>,
+}
+
+/// Owned result of [`JSONParser::parse_owned_with_pool`].
+#[derive(Debug, Clone)]
+pub struct PooledParseResult {
+    pub json: Vec<PooledEntry>,
+    pub max_json_depth: usize,
+}
+
+impl PooledParseResult {
+    pub fn find_node_at(&self, pointer: &str) -> Option<&PooledEntry> {
+        self.json.iter().find(|entry| entry.pointer.as_ref() == pointer)
+    }
+}
+
+impl JSONParser {
+    /// Like `parse_owned`, pointers being interned into `pool`, values too with `intern_values`: keep the same pool for all documents of a cache.
+    pub fn parse_owned_with_pool(input: &str, options: ParseOptions, pool: &mut KeyPool) -> Result<PooledParseResult, ParseError> {
+        // intern_values is rejected by other parses
+        let (res, canonical_numbers) = JSONParser::parse_with_canonical_numbers(input.as_bytes(), &ParseOptions { intern_values: false, ..options.clone() })?;
+        // not collected in place, which would retain the larger allocation of borrowed entries
+        let mut json = Vec::with_capacity(res.json.len());
+        json.extend(res.json.into_iter().map(|mut entry| {
+            let value = entry.value.map(|value| {
+                let value = owned_value(&options, &entry.pointer, value, &canonical_numbers);
+                // raw data of containers is never shared
+                if options.intern_values && entry.pointer.value_type.is_leaf() { pool.intern_value(&value) } else { Arc::from(value) }
+            });
+            PooledEntry {
+                pointer: pool.intern(&std::mem::take(&mut entry.pointer.pointer)),
+                key: entry.pointer,
+                value,
+            }
+        }));
+        Ok(PooledParseResult { json, max_json_depth: res.max_json_depth })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::key_pool::KeyPool;
+    use crate::{JSONParser, ParseOptions};
+
+    #[test]
+    fn parse_owned_with_pool() {
+        let mut pool = KeyPool::new();
+        let json = r#"{"id": 1, "name": "a", "tags": ["x", "y"]}"#;
+        let res = JSONParser::parse_owned_with_pool(json, ParseOptions::default(), &mut pool).unwrap();
+        let owned = JSONParser::parse(json, ParseOptions::default()).unwrap();
+        assert_eq!(res.json.iter().map(|e| (e.pointer.as_ref(), e.key.value_type, e.key.depth, e.key.position, e.value.as_deref())).collect::<Vec<_>>(),
+                   owned.json.iter().map(|e| (e.pointer.pointer.as_str(), e.pointer.value_type, e.pointer.depth, e.pointer.position, e.value)).collect::<Vec<_>>());
+        assert!(res.json.iter().all(|e| e.key.pointer.is_empty()));
+        assert_eq!(pool.len(), 5);
+
+        let other = JSONParser::parse_owned_with_pool(r#"{"name": "b", "id": 2}"#, ParseOptions::default(), &mut pool).unwrap();
+        assert!(Arc::ptr_eq(&other.json[0].pointer, &res.find_node_at("/name").unwrap().pointer));
+        assert!(Arc::ptr_eq(&other.json[1].pointer, &res.json[0].pointer));
+        assert_eq!(other.find_node_at("/id").unwrap().value.as_deref(), Some("2"));
+        assert_eq!(pool.len(), 5);
+        assert_eq!(pool.value_count(), 0);
+    }
+
+    #[test]
+    fn intern_values() {
+        let mut pool = KeyPool::new();
+        let json = r#"[{"country": "FR", "raw": {"a": 1}}, {"country": "DE"}, {"country": "FR"}]"#;
+        let options = || ParseOptions::default().max_depth(2).intern_values(true);
+        let res = JSONParser::parse_owned_with_pool(json, options(), &mut pool).unwrap();
+        let countries: Vec<&Arc<str>> = res.json.iter().filter(|e| e.pointer.ends_with("/country")).map(|e| e.value.as_ref().unwrap()).collect();
+        assert_eq!(countries.iter().map(|v| v.as_ref()).collect::<Vec<&str>>(), vec!["FR", "DE", "FR"]);
+        assert!(Arc::ptr_eq(countries[0], countries[2]));
+        // raw data of /0/raw is not interned
+        assert_eq!(pool.value_count(), 2);
+
+        let other = JSONParser::parse_owned_with_pool(r#"{"country": "DE"}"#, options(), &mut pool).unwrap();
+        assert!(Arc::ptr_eq(other.json[0].value.as_ref().unwrap(), countries[1]));
+        assert_eq!(pool.value_count(), 2);
+
+        let res = JSONParser::parse_owned_with_pool(json, ParseOptions::default().max_depth(2), &mut pool).unwrap();
+        let countries: Vec<&Arc<str>> = res.json.iter().filter(|e| e.pointer.ends_with("/country")).map(|e| e.value.as_ref().unwrap()).collect();
+        assert!(!Arc::ptr_eq(countries[0], countries[2]));
+    }
+}
@@ -1,3 +1,6 @@
+use std::borrow::Cow;
+
+use crate::error::ParseError;
 use crate::string_from_bytes;
 
 #[derive(Debug)]
@@ -24,7 +27,17 @@ impl<'json> SliceRead<'json> {
     pub fn new(slice: &'json [u8]) -> Self {
         SliceRead { slice, index: 0 }
     }
+    // a quote is escaped when preceded by an odd number of backslashes: `\"` is escaped, `\\"` is not
+    #[inline]
+    fn is_escaped(&self, quote_index: usize, string_start: usize) -> bool {
+        let mut backslashes = 0;
+        while quote_index - backslashes > string_start && self.slice[quote_index - backslashes - 1] == b'\\' {
+            backslashes += 1;
+        }
+        backslashes % 2 == 1
+    }
     #[inline]
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Option<u8> {
         if self.index < self.slice.len() {
             let result = self.slice[self.index];
@@ -89,6 +102,10 @@ impl<'json> SliceRead<'json> {
 
 pub struct Lexer<'json> {
     reader: SliceRead<'json>,
+    token_start: usize,
+    // offset of the first byte which is not valid UTF-8, once a token containing it was read
+    invalid_utf8: Option<usize>,
+    strict_numbers: bool,
 }
 
 
@@ -102,6 +119,39 @@ impl<'json> Lexer<'json> {
     pub fn new(input: &'json [u8]) -> Self {
         Lexer {
             reader: SliceRead::new(input),
+            token_start: 0,
+            invalid_utf8: None,
+            strict_numbers: false,
+        }
+    }
+
+    /// With `ParseOptions::strict_numbers`, number tokens also take exponents and a leading `+` or `.`, so that malformed numbers are read as one token.
+    pub fn strict_numbers(mut self, strict_numbers: bool) -> Self {
+        self.strict_numbers = strict_numbers;
+        self
+    }
+
+    // `start..end` of the input as a string, recording where it is not valid UTF-8, reading stopping as if the input ended
+    fn str_between(&mut self, start: usize, end: usize) -> Option<&'json str> {
+        let bytes = &self.reader.slice[start..end];
+        let value = string_from_bytes(bytes);
+        if value.is_none() && self.invalid_utf8.is_none() {
+            self.invalid_utf8 = Some(start + std::str::from_utf8(bytes).err().map_or(0, |e| e.valid_up_to()));
+        }
+        value
+    }
+
+    /// Offset of the first byte which is not valid UTF-8 among tokens read so far, such an input being read as if it was truncated there.
+    /// `parse_bytes` reports it as `ParseError::InvalidUtf8` instead of the `UnexpectedEof` which results.
+    pub fn invalid_utf8_offset(&self) -> Option<usize> {
+        self.invalid_utf8
+    }
+
+    // outcome of reading with this lexer, failing with `InvalidUtf8` when reading stopped on invalid UTF-8, even if the truncated input was accepted
+    pub(crate) fn utf8_checked<T>(&self, result: Result<T, ParseError>) -> Result<T, ParseError> {
+        match self.invalid_utf8 {
+            Some(offset) => Err(ParseError::InvalidUtf8 { offset }),
+            None => result,
         }
     }
 
@@ -115,23 +165,23 @@ impl<'json> Lexer<'json> {
             let current_index = self.reader.index;
             let (bytes, _) = self.reader.next_u64();
             let comparison = MASK_CLOSE_SQUARE ^ bytes;
-            let high_bit_mask1 = (((comparison >> 1) | 0x8080808080808080) - comparison) & 0x8080808080808080;
+            let close_mask = (((comparison >> 1) | 0x8080808080808080) - comparison) & 0x8080808080808080;
+            let comparison = MASK_OPEN_SQUARE ^ bytes;
+            let open_mask = (((comparison >> 1) | 0x8080808080808080) - comparison) & 0x8080808080808080;
+            let comparison = MASK_QUOTE ^ bytes;
+            let quote_mask = (((comparison >> 1) | 0x8080808080808080) - comparison) & 0x8080808080808080;
+            // move to the first of them, otherwise an opening preceding a closing in the same chunk would be missed, and skip strings which may contain any of them
+            let high_bit_mask1 = close_mask | open_mask | quote_mask;
             if high_bit_mask1 == 0 {
-                let comparison = MASK_OPEN_SQUARE ^ bytes;
-                let high_bit_mask1 = (((comparison >> 1) | 0x8080808080808080) - comparison) & 0x8080808080808080;
-                if high_bit_mask1 == 0 {
-                    continue;
-                } else {
-                    self.reader.index = current_index + (high_bit_mask1.trailing_zeros() >> 3) as usize;
-                }
-            } else {
-                self.reader.index = current_index + (high_bit_mask1.trailing_zeros() >> 3) as usize;
+                continue;
             }
+            self.reader.index = current_index + (high_bit_mask1.trailing_zeros() >> 3) as usize;
             match self.reader.next()? {
+                b'"' => self.skip_string(),
                 b'[' => square_close_count += 1,
                 b']' => {
                     if square_close_count == 1 {
-                        return string_from_bytes(&self.reader.slice[array_start_index..self.reader.index]);
+                        return self.str_between(array_start_index, self.reader.index);
                     } else {
                         square_close_count -= 1;
                     }
@@ -145,6 +195,13 @@ impl<'json> Lexer<'json> {
     pub fn reader_index(&self) -> usize {
         self.reader.index
     }
+    pub fn input_len(&self) -> usize {
+        self.reader.slice.len()
+    }
+    /// Offset of the first byte of the last token returned by `next_token`.
+    pub fn token_start(&self) -> usize {
+        self.token_start
+    }
     pub fn reader(&mut self) -> &SliceRead<'json> {
         &self.reader
     }
@@ -161,25 +218,25 @@ impl<'json> Lexer<'json> {
             let current_index = self.reader.index;
             let (bytes, _) = self.reader.next_u64();
             let comparison = MASK_CLOSE_CURLY ^ bytes;
-            let high_bit_mask1 = (((comparison >> 1) | 0x8080808080808080) - comparison) & 0x8080808080808080;
+            let close_mask = (((comparison >> 1) | 0x8080808080808080) - comparison) & 0x8080808080808080;
+            let comparison = MASK_OPEN_CURLY ^ bytes;
+            let open_mask = (((comparison >> 1) | 0x8080808080808080) - comparison) & 0x8080808080808080;
+            let comparison = MASK_QUOTE ^ bytes;
+            let quote_mask = (((comparison >> 1) | 0x8080808080808080) - comparison) & 0x8080808080808080;
+            // move to the first of them, otherwise an opening preceding a closing in the same chunk would be missed, and skip strings which may contain any of them
+            let high_bit_mask1 = close_mask | open_mask | quote_mask;
             if high_bit_mask1 == 0 {
-                let comparison = MASK_OPEN_CURLY ^ bytes;
-                let high_bit_mask1 = (((comparison >> 1) | 0x8080808080808080) - comparison) & 0x8080808080808080;
-                if high_bit_mask1 == 0 {
-                    continue;
-                } else {
-                    self.reader.index = current_index + (high_bit_mask1.trailing_zeros() >> 3) as usize;
-                }
-            } else {
-                self.reader.index = current_index + (high_bit_mask1.trailing_zeros() >> 3) as usize;
+                continue;
             }
+            self.reader.index = current_index + (high_bit_mask1.trailing_zeros() >> 3) as usize;
 
             match self.reader.next()? {
+                b'"' => self.skip_string(),
                 b'{' => square_close_count += 1,
                 b'}' => {
                     if square_close_count == 1 {
                         if should_return {
-                            let value = string_from_bytes(&self.reader.slice[start..self.reader.index])?;
+                            let value = self.str_between(start, self.reader.index)?;
                             return Some(value);
                         } else {
                             break;
@@ -193,9 +250,30 @@ impl<'json> Lexer<'json> {
         }
         None
     }
+    // move after the closing quote of the string whose opening quote has just been read
+    #[inline]
+    fn skip_string(&mut self) {
+        let start = self.reader.index;
+        while !self.reader.is_at_end() {
+            let (bytes, read_bytes) = self.reader.next_u64();
+            let comparison = MASK_QUOTE ^ bytes;
+            let high_bit_mask1 = (((comparison >> 1) | 0x8080808080808080) - comparison) & 0x8080808080808080;
+            if high_bit_mask1 != 0 {
+                let position = (high_bit_mask1.trailing_zeros() >> 3) as usize;
+                if !self.reader.is_escaped(self.reader.index - read_bytes + position, start) {
+                    self.reader.index = self.reader.index - read_bytes + position + 1;
+                    break;
+                } else {
+                    self.reader.index = self.reader.index - read_bytes + position + 1;
+                }
+            }
+        }
+    }
+
     #[inline]
     pub fn next_token(&mut self) -> Option<Token<'json>> {
         loop {
+            self.token_start = self.reader.index;
             match self.reader.next()? {
                 b'{' => return Some(Token::CurlyOpen),
                 b'}' => return Some(Token::CurlyClose),
@@ -205,33 +283,30 @@ impl<'json> Lexer<'json> {
                 b':' => return Some(Token::Colon),
                 b'-' | b'0' | b'1' | b'2' | b'3' | b'4' | b'5' | b'6' | b'7' | b'8' | b'9' => {
                     let start = self.reader.index - 1;
-                    while let Some(b) = self.reader.next() {
-                        if !((0x30..=0x39).contains(&b) || b == b'.') {
+                    while let Some(b) = self.reader.peek() {
+                        if !((0x30..=0x39).contains(&b) || b == b'.' || self.strict_numbers && matches!(b, b'e' | b'E' | b'+' | b'-')) {
                             break;
                         }
+                        self.reader.index += 1;
                     }
-                    self.reader.index -= 1;
                     let s = string_from_bytes(&self.reader.slice[start..self.reader.index])?;
                     return Some(Token::Number(s));
                 }
-                b'"' => {
-                    let start = self.reader.index;
-                    while !self.reader.is_at_end() {
-                        let (bytes, read_bytes) = self.reader.next_u64();
-                        let comparison = MASK_QUOTE ^ bytes;
-                        let high_bit_mask1 = (((comparison >> 1) | 0x8080808080808080) - comparison) & 0x8080808080808080;
-                        // println!("...{}", String::from_utf8_lossy(&self.reader.slice[self.reader.index - read_bytes..self.reader.index]));
-                        if high_bit_mask1 != 0 {
-                            let position = (high_bit_mask1.trailing_zeros() >> 3) as usize;
-                            if self.reader.slice[self.reader.index - read_bytes + position - 1] != b'\\' {
-                                self.reader.index = self.reader.index - read_bytes + position + 1;
-                                break;
-                            } else {
-                                self.reader.index = self.reader.index - read_bytes + position + 1;
-                            }
+                b'+' | b'.' if self.strict_numbers => {
+                    let start = self.reader.index - 1;
+                    while let Some(b) = self.reader.peek() {
+                        if !(b.is_ascii_digit() || matches!(b, b'.' | b'e' | b'E' | b'+' | b'-')) {
+                            break;
                         }
+                        self.reader.index += 1;
                     }
-                    let s = string_from_bytes(&self.reader.slice[start..self.reader.index - 1])?;
+                    let s = string_from_bytes(&self.reader.slice[start..self.reader.index])?;
+                    return Some(Token::Number(s));
+                }
+                b'"' => {
+                    let start = self.reader.index;
+                    self.skip_string();
+                    let s = self.str_between(start, self.reader.index - 1)?;
                     return Some(Token::String(s));
                 }
                 b't' if self.reader.match_pattern(b"rue") => return Some(Token::Boolean(string_from_bytes(&self.reader.slice[self.reader.index-4..self.reader.index])?)),
@@ -243,4 +318,87 @@ impl<'json> Lexer<'json> {
     }
 }
 
+/// String content whose escape sequences not allowed by JSON are read as the escaped character, i.e. `\q` as `q`, see [`find_invalid_escape`].
+pub fn drop_invalid_escapes(string: &str) -> Cow<'_, str> {
+    let Some(mut backslash) = find_invalid_escape(string) else {
+        return Cow::Borrowed(string);
+    };
+    let mut unescaped = String::with_capacity(string.len());
+    let mut rest = string;
+    loop {
+        unescaped.push_str(&rest[0..backslash]);
+        // the escaped character is never a backslash, `\\` being allowed
+        rest = &rest[backslash + 1..];
+        match find_invalid_escape(rest) {
+            Some(next) => backslash = next,
+            None => break,
+        }
+    }
+    unescaped.push_str(rest);
+    Cow::Owned(unescaped)
+}
 
+/// Index of the backslash of the first escape sequence not allowed by JSON in string content, i.e. other than `\" \\ \/ \b \f \n \r \t \uXXXX`.
+pub fn find_invalid_escape(string: &str) -> Option<usize> {
+    let bytes = string.as_bytes();
+    let mut i = 0;
+    while let Some(backslash) = bytes[i..].iter().position(|b| *b == b'\\') {
+        i += backslash;
+        match bytes.get(i + 1) {
+            Some(b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't') => i += 2,
+            Some(b'u') if bytes.len() >= i + 6 && bytes[i + 2..i + 6].iter().all(u8::is_ascii_hexdigit) => i += 6,
+            _ => return Some(i),
+        }
+    }
+    None
+}
+
+/// String content with its escape sequences replaced by the characters they stand for, e.g. to write a value to a format which is not JSON.
+/// Escapes not allowed by JSON are read as the escaped character like with [`drop_invalid_escapes`], a `\u` escape of a lone surrogate as `U+FFFD`.
+pub fn unescape(string: &str) -> Cow<'_, str> {
+    if !string.contains('\\') {
+        return Cow::Borrowed(string);
+    }
+    let mut unescaped = String::with_capacity(string.len());
+    let mut chars = string.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('b') => unescaped.push('\u{8}'),
+            Some('f') => unescaped.push('\u{c}'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some('t') => unescaped.push('\t'),
+            Some('u') => match hex_code_unit(chars.as_str()) {
+                Some(high @ 0xd800..=0xdbff) => {
+                    chars.nth(3);
+                    let low = chars.as_str().strip_prefix("\\u").and_then(hex_code_unit).filter(|low| (0xdc00..=0xdfff).contains(low));
+                    match low {
+                        Some(low) => {
+                            chars.nth(5);
+                            unescaped.push(char::from_u32(0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)).unwrap_or(char::REPLACEMENT_CHARACTER));
+                        }
+                        None => unescaped.push(char::REPLACEMENT_CHARACTER),
+                    }
+                }
+                Some(code_unit) => {
+                    chars.nth(3);
+                    unescaped.push(char::from_u32(code_unit).unwrap_or(char::REPLACEMENT_CHARACTER));
+                }
+                None => unescaped.push('u'),
+            },
+            Some(c) => unescaped.push(c),
+            None => {}
+        }
+    }
+    Cow::Owned(unescaped)
+}
+
+// code unit of the 4 hex digits starting `string`
+fn hex_code_unit(string: &str) -> Option<u32> {
+    let digits = string.get(0..4).filter(|digits| digits.bytes().all(|b| b.is_ascii_hexdigit()))?;
+    u32::from_str_radix(digits, 16).ok()
+}
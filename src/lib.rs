@@ -1,13 +1,35 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::{Debug};
 use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use std::str::FromStr;
+use std::sync::Arc;
 
-use crate::lexer::Lexer;
+use crate::error::{ParseError, TypeMismatch};
+use crate::lexer::{drop_invalid_escapes, Lexer};
 use crate::parser::Parser;
 use crate::serializer::{serialize_to_json, Value};
+use crate::string_subtype::StringSubtype;
 
 pub mod parser;
 pub mod lexer;
 pub mod serializer;
+pub mod csv;
+pub mod binary;
+#[cfg(feature = "dot")]
+pub mod dot;
+pub mod error;
+#[cfg(feature = "framing")]
+pub mod framing;
+pub mod events;
+pub mod projection;
+pub mod string_subtype;
+pub mod tree;
+pub mod validate;
+pub mod aggregate;
+pub mod key_pool;
 
 pub struct JSONParser {}
 
@@ -19,6 +41,77 @@ pub struct ParseOptions {
     pub start_parse_at: Option<String>,
     pub start_depth: u8,
     pub prefix: Option<String>,
+    pub emit_containers: bool,
+    pub duplicate_key_policy: DuplicateKeyPolicy,
+    pub detect_string_subtypes: bool,
+    pub max_steps: Option<u64>,
+    pub deny_unknown_escape: bool,
+    pub max_number_length: Option<usize>,
+    pub strict_numbers: bool,
+    pub key_transform: Option<KeyTransform>,
+    pub string_trim: StringTrim,
+    pub envelope_only: bool,
+    pub array_index_base: usize,
+    pub emit_keys: bool,
+    pub number_format: NumberFormat,
+    pub fold_single_key_objects: bool,
+    pub fold_separator: String,
+    pub truncate_string_values: Option<usize>,
+    pub max_open_containers: Option<usize>,
+    pub annotate_roles: bool,
+    pub progress: Option<ProgressCallback>,
+    pub progress_interval: usize,
+    pub intern_values: bool,
+}
+
+/// Whitespace trimmed from both ends of string values.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum StringTrim {
+    #[default]
+    None,
+    /// Space, tab, line feed, form feed and carriage return.
+    Ascii,
+    /// Characters with the Unicode `White_Space` property, e.g. no-break space.
+    Unicode,
+}
+
+impl StringTrim {
+    pub fn trim(self, value: &str) -> &str {
+        match self {
+            StringTrim::None => value,
+            StringTrim::Ascii => value.trim_ascii(),
+            StringTrim::Unicode => value.trim(),
+        }
+    }
+}
+
+/// Recognizer of number tokens, see [`ParseOptions::number_format`]: the canonical JSON number for a raw token, or `None` when it is not a number.
+pub type NumberRecognizer = Arc<dyn Fn(&str) -> Option<Cow<'_, str>> + Send + Sync>;
+
+/// How number tokens are recognized.
+#[derive(Clone, Default)]
+pub enum NumberFormat {
+    /// Numbers as defined by JSON.
+    #[default]
+    Json,
+    /// Non-standard numbers, e.g. `1.234.567` with `.` as thousands separator, recognized and normalized by a custom function.
+    Custom(NumberRecognizer),
+}
+
+/// Progress of a parse, see [`ParseOptions::progress`]: called with `(bytes_consumed, total_bytes)`, `total_bytes` being 0 when unknown.
+pub type ProgressCallback = Arc<dyn Fn(usize, usize) + Send + Sync>;
+
+/// Rewrite of object keys, see [`ParseOptions::key_transform`].
+pub type KeyTransform = Arc<dyn Fn(&str) -> Cow<'_, str> + Send + Sync>;
+
+/// How to handle a key appearing more than once in the same object.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateKeyPolicy {
+    /// Every occurrence is emitted.
+    #[default]
+    Allow,
+    /// A later occurrence replaces entries of the earlier one, in place: the value is the last one while the entry keeps the position of the first occurrence.
+    LastValueFirstPosition,
 }
 
 impl Default for ParseOptions {
@@ -30,6 +123,27 @@ impl Default for ParseOptions {
             start_parse_at: None,
             start_depth: 1,
             prefix: None,
+            emit_containers: true,
+            duplicate_key_policy: DuplicateKeyPolicy::Allow,
+            detect_string_subtypes: false,
+            max_steps: None,
+            deny_unknown_escape: true,
+            max_number_length: None,
+            strict_numbers: false,
+            key_transform: None,
+            string_trim: StringTrim::None,
+            envelope_only: false,
+            array_index_base: 0,
+            emit_keys: false,
+            number_format: NumberFormat::Json,
+            fold_single_key_objects: false,
+            fold_separator: ".".to_string(),
+            truncate_string_values: None,
+            max_open_containers: None,
+            annotate_roles: false,
+            progress: None,
+            progress_interval: 4 * 1024 * 1024,
+            intern_values: false,
         }
     }
 }
@@ -60,6 +174,201 @@ impl ParseOptions {
         self.keep_object_raw_data = keep_object_raw_data;
         self
     }
+    /// When false, `Object` and `Array` entries are not emitted and the result only contains scalar leaves.
+    /// This is lossy: empty containers and containers beyond `max_depth` disappear from the output, so they can't be reserialized nor expanded with `change_depth`.
+    pub fn emit_containers(mut self, emit_containers: bool) -> Self {
+        self.emit_containers = emit_containers;
+        self
+    }
+    pub fn duplicate_key_policy(mut self, duplicate_key_policy: DuplicateKeyPolicy) -> Self {
+        self.duplicate_key_policy = duplicate_key_policy;
+        self
+    }
+    /// When true, string values looking like a UUID, an email or a URL get a `string_subtype`, see [`StringSubtype`] for patterns used. Values are untouched.
+    pub fn detect_string_subtypes(mut self, detect_string_subtypes: bool) -> Self {
+        self.detect_string_subtypes = detect_string_subtypes;
+        self
+    }
+    /// Deterministic budget: parsing fails with `ParseError::LimitExceeded { limit: "max_steps", .. }` once more than `max_steps` values have been read,
+    /// a value skipped beyond `max_depth` counting as one step. Unlike a timeout, the outcome is reproducible across runs and platforms.
+    pub fn max_steps(mut self, max_steps: u64) -> Self {
+        self.max_steps = Some(max_steps);
+        self
+    }
+    /// Containers open at the same time, i.e. nesting of objects and arrays, the root one included, beyond which reading fails with `ParseError::LimitExceeded { limit: "max_open_containers", .. }`.
+    /// Unlike `max_depth`, it bounds resources used by the reader instead of what is flattened, which matters for streams whose size is not known up front, see `JSONParser::validate_stream_with_options`.
+    /// Containers skipped beyond `max_depth` are not read, so their content is not counted.
+    pub fn max_open_containers(mut self, max_open_containers: usize) -> Self {
+        self.max_open_containers = Some(max_open_containers);
+        self
+    }
+    /// When true, entries get the `role` they have in the document, a root container, an array element or an object member, as recorded while parsing instead of being inferred
+    /// from their pointer and their parent, to debug flattening or to build custom reconstructors, see [`ParseResult::validate_pointers`]. Off by default.
+    /// Roles are recorded by `parse` and `change_depth`, not by `parse_projection`.
+    pub fn annotate_roles(mut self, annotate_roles: bool) -> Self {
+        self.annotate_roles = annotate_roles;
+        self
+    }
+    /// Callback reporting progress of a long parse, e.g. for a progress bar: called with `(bytes_consumed, total_bytes)` each time `progress_interval` more bytes were consumed, and once at the end
+    /// with `bytes_consumed == total_bytes`. Progress is checked when a value is read, so it is not reported while skipping a large container beyond `max_depth`.
+    /// Reported by `parse` and by `validate_stream_with_options`, which doesn't know the total and passes 0, neither by `change_depth` nor by `parse_projection`. Off by default.
+    pub fn progress(mut self, progress: ProgressCallback) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+    /// Bytes consumed between two calls of the `progress` callback, 4 MiB by default, keeping its cost negligible.
+    pub fn progress_interval(mut self, progress_interval: usize) -> Self {
+        self.progress_interval = progress_interval;
+        self
+    }
+    /// When true, identical scalar values of results of `parse_owned_with_pool` share one allocation from its pool, at the cost of a hash lookup per value. Off by default,
+    /// other parses rejecting it with `ParseError::InvalidOptions`, see `validate`.
+    pub fn intern_values(mut self, intern_values: bool) -> Self {
+        self.intern_values = intern_values;
+        self
+    }
+    /// When true (default), an escape sequence not allowed by JSON, e.g. `\q`, fails parsing with `ParseError::InvalidEscape`. When false it is read as the escaped character, `q`,
+    /// in pointers and in values of `parse_owned`, values borrowing the input keeping it as written. Escapes in containers skipped beyond `max_depth` are not checked.
+    pub fn deny_unknown_escape(mut self, deny_unknown_escape: bool) -> Self {
+        self.deny_unknown_escape = deny_unknown_escape;
+        self
+    }
+    /// Numbers longer than `max_number_length` bytes, sign and decimal point included, fail parsing with `ParseError::NumberTooLong`.
+    /// Without it a number of any length is kept as is, scanning it being linear in its length.
+    pub fn max_number_length(mut self, max_number_length: usize) -> Self {
+        self.max_number_length = Some(max_number_length);
+        self
+    }
+    /// When true, numbers follow the strict grammar of RFC 8259 checked by `validate_stream`: exponents such as `1e5` or `1.5E-3` are read, while leading zeros such as `01`
+    /// and malformed numbers such as `+1` or `.5` fail with `ParseError::InvalidNumber`. Off by default, numbers being digits with an optional leading `-` and `.`, leading zeros included.
+    pub fn strict_numbers(mut self, strict_numbers: bool) -> Self {
+        self.strict_numbers = strict_numbers;
+        self
+    }
+    /// Rewrite each object key before it becomes a pointer segment, e.g. to strip a leading `@` or replace spaces, so pointers of the result are already normalized.
+    /// Keys are rewritten as written in the input, escape sequences included. Pointers given to `start_parse_at`, `parse_projection` or to detect duplicates are compared with rewritten keys.
+    pub fn key_transform(mut self, key_transform: KeyTransform) -> Self {
+        self.key_transform = Some(key_transform);
+        self
+    }
+    /// When true, ASCII whitespace is trimmed from both ends of string values, keys and other values are untouched. Off by default.
+    /// Values are slices of the input which are not unescaped, so an escaped whitespace like `\n` is kept.
+    pub fn trim_string_values(mut self, trim_string_values: bool) -> Self {
+        self.string_trim = if trim_string_values { StringTrim::Ascii } else { StringTrim::None };
+        self
+    }
+    /// Like `trim_string_values`, choosing which whitespace is trimmed.
+    pub fn string_trim(mut self, string_trim: StringTrim) -> Self {
+        self.string_trim = string_trim;
+        self
+    }
+    /// When true, only the root container entry, pointer `""`, and its direct children are emitted, containers among them with their raw data: the cheapest probe of the fields of a document.
+    /// Compared to `max_depth(1)`, the root object gets an entry of its own (`Object(true)`, depth `start_depth - 1`) like the root array does, and `max_depth`, `keep_object_raw_data`, `emit_containers` and `parse_array` are ignored.
+    /// Depth accounting is unchanged, root array elements sharing the depth of the root array, so children have `start_depth` depth and can be expanded with `change_depth`.
+    pub fn envelope_only(mut self, envelope_only: bool) -> Self {
+        self.envelope_only = envelope_only;
+        self
+    }
+    /// Index of the first array element in pointers, 0 (default) or 1, e.g. `/items/1/id` for the first item with 1, as expected by some SQL or spreadsheet exports.
+    /// Only the rendered index segment, and the `index` of entries, are affected. With 1, pointers are not valid JSON Pointers (RFC 6901) and can't round-trip through standard pointer tools,
+    /// pointers given to `start_parse_at`, `parse_projection` or `parse_value_at` being compared with rendered ones.
+    pub fn array_index_base(mut self, array_index_base: usize) -> Self {
+        self.array_index_base = array_index_base;
+        self
+    }
+    /// When true, entries of object members get the `key_span` of their key: its byte range in the input, quotes excluded, so that keys can be rewritten in the original text.
+    /// The key is the one as written, before `key_transform`. Only `parse` records spans, and spans of entries added by `change_depth` are relative to the raw data they were expanded from.
+    pub fn emit_keys(mut self, emit_keys: bool) -> Self {
+        self.emit_keys = emit_keys;
+        self
+    }
+    /// Relaxed, non-standard numbers for carelessly generated exports: with `NumberFormat::Custom`, the recognizer replaces JSON number validation, returning `None` failing with `ParseError::InvalidNumber`.
+    /// It only gets number tokens as delimited by JSON, i.e. a leading `-`, digits and `.`, exponents too with `strict_numbers`: `,` is always an element delimiter, so `[1,5]` holds two numbers whatever the recognizer,
+    /// and a quoted `"1.234,56"` is a string. Canonical numbers are only stored in owned results (`parse_owned`, `parse_owned_with_pool`, `change_depth_owned`), borrowed ones like `parse` and `parse_projection` keeping numbers as written.
+    /// Numbers are ambiguous across formats, `1.234` being a thousand or a decimal, so only use a recognizer for inputs known to follow its format.
+    pub fn number_format(mut self, number_format: NumberFormat) -> Self {
+        self.number_format = number_format;
+        self
+    }
+    /// When true, chains of objects having a single key are collapsed into one pointer segment joined with `fold_separator`, e.g. `{"a": {"b": {"c": 1}}}` as the single entry `/a.b.c`,
+    /// to reduce the noise of documents converted from XML. Folded objects have no entry and depths of entries below them are reduced accordingly. Elements of arrays are never folded into their index.
+    /// This is lossy: `/a.b.c` can't be told apart from a key named `a.b.c`, so folded results can't be reserialized to the original document. Off by default,
+    /// it is applied by `parse` once the document is parsed, thus objects beyond `max_depth` and results without container entries are not folded.
+    pub fn fold_single_key_objects(mut self, fold_single_key_objects: bool) -> Self {
+        self.fold_single_key_objects = fold_single_key_objects;
+        self
+    }
+    /// Preview of long string values, e.g. for a log viewer: values longer than `max_len` bytes are cut to at most `max_len` bytes, not in the middle of a character nor of an escape sequence,
+    /// and their entry gets the byte length of the whole value as `original_len`. Unlike a limit, this is not an error. Owned results (`parse_owned`, `parse_owned_with_pool`, `change_depth_owned`)
+    /// append the `…` marker to truncated values, values of `parse` and `parse_projection` borrowing the input they are just shortened, `original_len` telling they were cut. Truncated values are lost: the result can't be reserialized to the original document.
+    pub fn truncate_string_values(mut self, max_len: Option<usize>) -> Self {
+        self.truncate_string_values = max_len;
+        self
+    }
+    // pointer segment of an object key as written in the input
+    pub(crate) fn key_segment<'a>(&self, key: &'a str) -> Cow<'a, str> {
+        let key = if self.deny_unknown_escape { Cow::Borrowed(key) } else { drop_invalid_escapes(key) };
+        match (&self.key_transform, key) {
+            (Some(key_transform), Cow::Borrowed(key)) => key_transform(key),
+            (Some(key_transform), Cow::Owned(key)) => Cow::Owned(key_transform(&key).into_owned()),
+            (None, key) => key,
+        }
+    }
+    // the value to store, with its original length when truncated
+    pub(crate) fn truncate_string<'a>(&self, value: &'a str) -> (&'a str, Option<usize>) {
+        let Some(max_len) = self.truncate_string_values.filter(|max_len| value.len() > *max_len) else {
+            return (value, None);
+        };
+        let mut end = max_len;
+        while !value.is_char_boundary(end) {
+            end -= 1;
+        }
+        let bytes = value.as_bytes();
+        let mut i = 0;
+        while let Some(backslash) = bytes[i..end].iter().position(|b| *b == b'\\') {
+            i += backslash;
+            let escape_len = if bytes.get(i + 1) == Some(&b'u') { 6 } else { 2 };
+            if i + escape_len > end {
+                end = i;
+                break;
+            }
+            i += escape_len;
+        }
+        (&value[0..end], Some(value.len()))
+    }
+    /// Check combinations of options which are contradictory or meaningless, done by `parse` and `parse_projection` before reading input, failing with `ParseError::InvalidOptions`. Flagged are:
+    /// - a `prefix` or a `start_parse_at` pointer not starting with `/`, which would produce invalid pointers or never match,
+    /// - an `array_index_base` other than 0 or 1,
+    /// - `fold_single_key_objects` with `emit_containers(false)`, objects being folded from their entries, or with an empty `fold_separator`, which would join keys ambiguously,
+    /// - `intern_values` outside of `parse_owned_with_pool`, the only parse having a pool to intern values into.
+    ///
+    /// Options ignored in some modes, e.g. `max_depth` with `envelope_only`, are not flagged.
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(prefix) = self.prefix.as_deref().filter(|prefix| !prefix.is_empty() && !prefix.starts_with('/')) {
+            return Err(format!("prefix {:?} should start with '/'", prefix));
+        }
+        if let Some(pointer) = self.start_parse_at.as_deref().filter(|pointer| !pointer.starts_with('/')) {
+            return Err(format!("start_parse_at pointer {:?} should start with '/'", pointer));
+        }
+        if self.array_index_base > 1 {
+            return Err(format!("array_index_base should be 0 or 1, not {}", self.array_index_base));
+        }
+        if self.fold_single_key_objects && !self.emit_containers {
+            return Err("fold_single_key_objects has no effect with emit_containers(false), objects are folded from their entries".to_string());
+        }
+        if self.fold_single_key_objects && self.fold_separator.is_empty() {
+            return Err("fold_separator should not be empty with fold_single_key_objects, folded keys would be ambiguous".to_string());
+        }
+        if self.intern_values {
+            return Err("intern_values is only applied by parse_owned_with_pool".to_string());
+        }
+        Ok(())
+    }
+    /// Separator of keys folded by `fold_single_key_objects`, `.` by default. With `/` pointers and depths are the ones of the original document, folded objects just having no entry.
+    pub fn fold_separator(mut self, fold_separator: &str) -> Self {
+        self.fold_separator = fold_separator.to_string();
+        self
+    }
 }
 
 pub trait GetBytes {
@@ -78,6 +387,12 @@ impl GetBytes for &str {
     }
 }
 
+impl GetBytes for Arc<str> {
+    fn get_bytes(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct JsonArrayEntries<V: Debug + Clone + AsRef<str> + GetBytes> {
     pub entries: Vec<FlatJsonValue<V>>,
@@ -97,6 +412,68 @@ impl<V: Debug + Clone + AsRef<str> + GetBytes> JsonArrayEntries<V> {
     }
 }
 
+/// Breadth of a document, see [`ParseResult::breadth_stats`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BreadthStats {
+    pub max_array_len: usize,
+    pub max_object_keys: usize,
+    pub total_arrays: usize,
+    pub total_objects: usize,
+    /// Sum of the lengths of all arrays.
+    pub total_array_elements: usize,
+    /// Sum of the key counts of all objects.
+    pub total_object_keys: usize,
+}
+
+impl BreadthStats {
+    fn add_object(&mut self, keys: usize) {
+        self.total_objects += 1;
+        self.total_object_keys += keys;
+        self.max_object_keys = self.max_object_keys.max(keys);
+    }
+    fn add_array(&mut self, len: usize) {
+        self.total_arrays += 1;
+        self.total_array_elements += len;
+        self.max_array_len = self.max_array_len.max(len);
+    }
+    pub fn average_array_len(&self) -> f64 {
+        if self.total_arrays == 0 { 0.0 } else { self.total_array_elements as f64 / self.total_arrays as f64 }
+    }
+    pub fn average_object_keys(&self) -> f64 {
+        if self.total_objects == 0 { 0.0 } else { self.total_object_keys as f64 / self.total_objects as f64 }
+    }
+}
+
+/// Borrowed entries of one array element, see [`ParseResult::rows`].
+#[derive(Debug, Clone, Copy)]
+pub struct RowView<'a, V: Debug + Clone + AsRef<str> + GetBytes> {
+    pub entries: &'a [FlatJsonValue<V>],
+    pub index: usize,
+}
+
+impl<'a, V: Debug + Clone + AsRef<str> + GetBytes> RowView<'a, V> {
+    pub fn entries(&self) -> &'a [FlatJsonValue<V>] {
+        self.entries
+    }
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn find_node_at(&self, pointer: &str) -> Option<&'a FlatJsonValue<V>> {
+        self.entries.iter().find(|v| v.pointer.pointer.eq(pointer))
+    }
+}
+
+/// Shape of a parsed document, see [`ParseResult::dispatch`].
+#[derive(Debug, Clone)]
+pub enum RootKind<V: Debug + Clone + AsRef<str> + GetBytes> {
+    /// Entries of each root array element, element itself included, by index.
+    Array(Vec<JsonArrayEntries<V>>),
+    /// Entries of the root object, without its own entry when there is one.
+    Object(Vec<FlatJsonValue<V>>),
+    Scalar(FlatJsonValue<V>),
+}
+
 impl<V: Debug + Clone + AsRef<str> + GetBytes> Hash for JsonArrayEntries<V> {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.index.hash(state);
@@ -112,6 +489,24 @@ pub struct PointerKey {
     pub depth: u8,    // depth of the pointed value in the json
     pub index: usize, // index in the root json array
     pub position: usize, // position on the original json
+    pub string_subtype: Option<StringSubtype>, // only detected with ParseOptions::detect_string_subtypes
+    pub key_span: Option<Range<usize>>, // byte range of the key in the input, quotes excluded, only with ParseOptions::emit_keys
+    pub original_len: Option<usize>, // byte length of a string value truncated by ParseOptions::truncate_string_values
+    pub role: Option<PointerRole>, // only with ParseOptions::annotate_roles
+    pub key_count: Option<usize>, // keys of an object entry, like Array(len) for arrays, set by parse also for objects not expanded at max_depth
+}
+
+/// Place of an entry in the document, see [`ParseOptions::annotate_roles`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PointerRole {
+    /// The root array entry `""`.
+    RootArray,
+    /// The root object entry `""`, only emitted with `envelope_only`.
+    RootObject,
+    /// A value of an array, container or not.
+    ArrayElement,
+    /// A value of an object, container or not.
+    ObjectMember,
 }
 
 impl PartialEq<Self> for PointerKey {
@@ -138,6 +533,31 @@ impl PointerKey {
             &self.pointer[0..index]
         }) as _
     }
+
+    /// Depth derived from the pointer, its number of segments. It is `depth` for entries parsed with default `start_depth` and without `prefix`,
+    /// except for the root array entry `""` which has the depth of its elements. Otherwise all entries but `""` differ from `depth` by the same amount.
+    pub fn computed_depth(&self) -> u8 {
+        self.pointer.matches('/').count() as u8
+    }
+
+    // `depth` minus `computed_depth`, the same for all entries of a result but the `""` one
+    pub(crate) fn depth_offset(&self) -> i32 {
+        self.depth as i32 - self.computed_depth() as i32
+    }
+
+    /// Pointer relative to `prefix`, without the separating `/`, e.g. `/skills/0/name` relative to `/skills` is `0/name`.
+    /// Returns `None` when this pointer is not `prefix` itself or one of its descendants, `/skills_old` is not under `/skills`.
+    pub fn relative_to(&self, prefix: &str) -> Option<&str> {
+        if prefix.is_empty() || prefix == "/" {
+            return Some(self.pointer.strip_prefix('/').unwrap_or(&self.pointer));
+        }
+        let remainder = self.pointer.strip_prefix(prefix)?;
+        if remainder.is_empty() {
+            Some(remainder)
+        } else {
+            remainder.strip_prefix('/')
+        }
+    }
 }
 #[macro_export]
 macro_rules! concat_string {
@@ -153,10 +573,13 @@ macro_rules! concat_string {
 }
 macro_rules! change_depth {
     ($($t:ty, $func:ident, $to_owned:expr),+) => {$(
-    pub fn $func<'json>(previous_parse_result: &mut ParseResult<$t>, mut parse_options: ParseOptions) -> Result<(), String> {
+    #[allow(clippy::extra_unused_lifetimes)]
+    pub fn $func<'json>(previous_parse_result: &mut ParseResult<$t>, mut parse_options: ParseOptions) -> Result<(), ParseError> {
         let previous_parse_depth = previous_parse_result.parsing_max_depth;
         let previous_max_json_depth = previous_parse_result.max_json_depth;
         previous_parse_result.parsing_max_depth = parse_options.max_depth;
+        // progress of raw data would not be the one of the document
+        parse_options.progress = None;
         if previous_parse_depth < parse_options.max_depth {
             let previous_len = previous_parse_result.json.len();
             for i in 0..previous_len {
@@ -180,12 +603,13 @@ macro_rules! change_depth {
                 };
 
                 if should_parse {
+                    let depth_offset = entry.pointer.depth_offset();
                     if let Some(ref v) = entry.value {
-                        let mut lexer = Lexer::new(v.as_bytes());
+                        let mut lexer = Lexer::new(v.as_bytes()).strict_numbers(parse_options.strict_numbers);
                         let mut parser = Parser::new_for_change_depth(&mut lexer, previous_parse_result.depth_after_start_at, previous_max_json_depth);
                         parse_options.prefix = Some(entry.pointer.pointer.clone());
-                        let res = parser.parse(&parse_options, new_depth).unwrap();
-                        let mut res = $to_owned(res);
+                        let res = parser.parse(&parse_options, new_depth)?;
+                        let mut res = $to_owned(res, &parse_options, &parser.canonical_numbers);
                         if previous_parse_result.max_json_depth < res.max_json_depth {
                             previous_parse_result.max_json_depth = res.max_json_depth;
                         }
@@ -206,6 +630,7 @@ macro_rules! change_depth {
                         if is_object {
                             previous_parse_result.json[i].pointer.value_type = ValueType::Object(true);
                         }
+                        debug_assert!(res.json.iter().all(|entry| entry.pointer.depth_offset() == depth_offset), "expanded entries of {:?} have a depth inconsistent with their pointer", previous_parse_result.json[i].pointer.pointer);
 
                         previous_parse_result.json.extend(res.json);
                     }
@@ -227,6 +652,11 @@ impl PointerKey {
             depth,
             position,
             index: 0,
+            string_subtype: None,
+            key_span: None,
+            original_len: None,
+            role: None,
+            key_count: None,
         }
     }
     pub fn from_pointer_and_index(pointer: String, value_type: ValueType, depth: u8, index: usize, position: usize) -> Self {
@@ -236,6 +666,11 @@ impl PointerKey {
             depth,
             index,
             position,
+            string_subtype: None,
+            key_span: None,
+            original_len: None,
+            role: None,
+            key_count: None,
         }
     }
 }
@@ -248,13 +683,99 @@ pub enum ValueType {
     Number,
     String,
     Bool,
+    /// A JSON `null`, always serialized as `null`.
     Null,
+    /// No value at all, i.e. an absent field: never produced by the parser, this is the type of a default `PointerKey` and of entries added by hand as placeholders.
+    /// The serializer skips these entries, so they never materialize as `null`.
     #[default]
     None,
 }
 
+impl ValueType {
+    /// `Array` and `Object` are containers, even when kept as raw data.
+    pub fn is_container(&self) -> bool {
+        matches!(self, ValueType::Array(_) | ValueType::Object(_))
+    }
 
-type PointerFragment = Vec<String>;
+    /// Every type but containers is a leaf, `Null` and `None` included.
+    pub fn is_leaf(&self) -> bool {
+        !self.is_container()
+    }
+
+    /// Total order of types: `None` < `Null` < `Bool` < `Number` < `Array` < `Object` < `String`.
+    pub fn rank(&self) -> u8 {
+        match self {
+            ValueType::None => 0,
+            ValueType::Null => 1,
+            ValueType::Bool => 2,
+            ValueType::Number => 3,
+            ValueType::Array(_) => 4,
+            ValueType::Object(_) => 5,
+            ValueType::String => 6,
+        }
+    }
+
+    /// Type of a column holding values of both types `a` and `b`, following this lattice:
+    /// - `None` then `Null` are identities: `X + Null = X`, `None + Null = Null`
+    /// - same kinds are kept: `Array(a) + Array(b) = Array(max(a, b))`, `Object(a) + Object(b) = Object(a && b)`
+    /// - any other combination, e.g. `Bool + Number`, `Number + String` or `Array + Object`, is `String`, all values being representable as text
+    ///
+    /// The result is commutative and its rank is never lower than the rank of `a` and `b`.
+    pub fn promote(a: ValueType, b: ValueType) -> ValueType {
+        match (a, b) {
+            (ValueType::None, other) | (other, ValueType::None) => other,
+            (ValueType::Null, other) | (other, ValueType::Null) => other,
+            (ValueType::Array(a), ValueType::Array(b)) => ValueType::Array(a.max(b)),
+            (ValueType::Object(a), ValueType::Object(b)) => ValueType::Object(a && b),
+            (a, b) if a == b => a,
+            _ => ValueType::String,
+        }
+    }
+}
+
+
+/// Pointer of the value being parsed: segments are appended to a single buffer which is truncated when leaving them, so walking the document does not allocate per key.
+pub(crate) struct PointerFragment {
+    buffer: String,
+    segments_start: Vec<usize>,
+}
+
+impl PointerFragment {
+    pub(crate) fn with_capacity(capacity: usize) -> Self {
+        Self { buffer: String::with_capacity(capacity), segments_start: Vec::with_capacity(16) }
+    }
+    #[inline]
+    pub(crate) fn push(&mut self, segment: &str) {
+        self.segments_start.push(self.buffer.len());
+        self.buffer.push_str(segment);
+    }
+    #[inline]
+    pub(crate) fn push_key(&mut self, key: &str) {
+        self.segments_start.push(self.buffer.len());
+        self.buffer.push('/');
+        self.buffer.push_str(key);
+    }
+    #[inline]
+    pub(crate) fn push_index(&mut self, index: usize) {
+        use std::fmt::Write;
+        self.segments_start.push(self.buffer.len());
+        let _ = write!(self.buffer, "/{}", index);
+    }
+    #[inline]
+    pub(crate) fn pop(&mut self) {
+        if let Some(start) = self.segments_start.pop() {
+            self.buffer.truncate(start);
+        }
+    }
+    #[inline]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.segments_start.is_empty()
+    }
+    #[inline]
+    pub(crate) fn as_str(&self) -> &str {
+        &self.buffer
+    }
+}
 
 
 #[derive(Debug, Clone, Default)]
@@ -264,6 +785,21 @@ pub struct FlatJsonValue<V: Debug + Clone + AsRef<str> + GetBytes> {
 }
 
 
+impl FlatJsonValue<&str> {
+    /// Byte range of the value in the original `input`, e.g. to highlight it in an editor.
+    /// Values are never copied nor unescaped by the parser, so the range covers the source text as written (escape sequences included, quotes excluded) and is not shifted by a leading BOM.
+    /// This also holds for entries added by `change_depth`, as their values are slices of the raw data they were expanded from.
+    /// Returns `None` when there is no value or when it does not come from `input`.
+    pub fn span_in(&self, input: &str) -> Option<Range<usize>> {
+        let value = self.value?;
+        let start = (value.as_ptr() as usize).checked_sub(input.as_ptr() as usize)?;
+        if start + value.len() > input.len() {
+            return None;
+        }
+        Some(start..start + value.len())
+    }
+}
+
 impl<V: Debug + Clone + AsRef<str> + GetBytes>  Hash for FlatJsonValue<V> {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.pointer.hash(state);
@@ -279,6 +815,638 @@ pub struct ParseResult<V: Debug + Clone + AsRef<str> + GetBytes> {
     pub started_parsing_at: Option<String>,
     pub parsing_prefix: Option<String>,
     pub depth_after_start_at: u8,
+    /// Span of the root value in the parsed input, from its first byte, after leading whitespace or BOM, to its end.
+    pub source_span: Range<usize>,
+}
+
+impl<V: Debug + Clone + AsRef<str> + GetBytes> ParseResult<V> {
+    /// Entries of `current` whose pointer is not in `previous`, values are ignored. Useful to detect fields which appeared between two versions of a document.
+    pub fn subtract<'a, V2: Debug + Clone + AsRef<str> + GetBytes>(current: &'a ParseResult<V>, previous: &ParseResult<V2>) -> Vec<&'a FlatJsonValue<V>> {
+        let previous_pointers: HashSet<&str> = previous.json.iter().map(|entry| entry.pointer.pointer.as_str()).collect();
+        current.json.iter().filter(|entry| !previous_pointers.contains(entry.pointer.pointer.as_str())).collect()
+    }
+
+    /// True when both results have the same entries, compared as a multiset of `(pointer, value_type, value)`: order of entries, depths, positions and indexes don't matter.
+    /// With `semantic_numbers`, numbers are compared by their `f64` value so `1`, `1.0` and `1.00` are equal, otherwise as written.
+    pub fn structurally_equal(&self, other: &Self, semantic_numbers: bool) -> bool {
+        if self.json.len() != other.json.len() {
+            return false;
+        }
+        let key = |entry: &FlatJsonValue<V>| -> (String, ValueType, Option<String>) {
+            let value = entry.value.as_ref().map(|value| {
+                let value = value.as_ref();
+                match value.parse::<f64>() {
+                    // adding 0.0 turns -0 into 0
+                    Ok(number) if semantic_numbers && matches!(entry.pointer.value_type, ValueType::Number) => (number + 0.0).to_string(),
+                    _ => value.to_string(),
+                }
+            });
+            (entry.pointer.pointer.clone(), entry.pointer.value_type, value)
+        };
+        let mut counts: HashMap<(String, ValueType, Option<String>), isize> = HashMap::with_capacity(self.json.len());
+        for entry in self.json.iter() {
+            *counts.entry(key(entry)).or_default() += 1;
+        }
+        for entry in other.json.iter() {
+            match counts.get_mut(&key(entry)) {
+                Some(count) if *count > 0 => *count -= 1,
+                _ => return false,
+            }
+        }
+        true
+    }
+
+    /// Scalar entries, i.e. entries which are not an object nor an array. `Null` and `None` typed entries are included.
+    pub fn iter_leaves(&self) -> impl Iterator<Item=&FlatJsonValue<V>> {
+        self.json.iter().filter(|entry| entry.pointer.value_type.is_leaf())
+    }
+
+    /// Owned `(pointer, value)` pairs of scalar leaves, in entries order, e.g. for a key-value store or a path,value export. `Null` leaves have a `None` value,
+    /// entries typed `None` are absent values and are skipped. Containers are never included, containers beyond `max_depth` neither, the same holds with `emit_containers(false)`.
+    pub fn leaf_pairs(&self) -> Vec<(String, Option<String>)> {
+        self.iter_leaves()
+            .filter(|entry| !matches!(entry.pointer.value_type, ValueType::None))
+            .map(|entry| (entry.pointer.pointer.clone(), entry.value.as_ref().map(|value| value.as_ref().to_string())))
+            .collect()
+    }
+
+    /// Compact representation for analytics on huge documents: each distinct pointer is stored once and entries refer to it by a `u32` id, so comparing entries compares integers.
+    /// Depth, position, index and other fields of `PointerKey` are dropped, the representation can't be turned back into a result.
+    pub fn with_interned_pointers(self) -> InternedPointers<V> {
+        let mut pointers: Vec<String> = Vec::new();
+        let mut ids: HashMap<String, u32> = HashMap::new();
+        let entries = self.json.into_iter().map(|entry| {
+            let id = match ids.get(&entry.pointer.pointer) {
+                Some(id) => *id,
+                None => {
+                    let id = pointers.len() as u32;
+                    ids.insert(entry.pointer.pointer.clone(), id);
+                    pointers.push(entry.pointer.pointer);
+                    id
+                }
+            };
+            InternedEntry { id, value_type: entry.pointer.value_type, value: entry.value }
+        }).collect();
+        InternedPointers { entries, pointers, ids }
+    }
+
+    /// First entry in document order, which is not always the first of `json`, e.g. once `change_depth` appended expanded entries.
+    /// It is the root when the root has an entry: the root array `""`, also with a `prefix`, the root object `""` with `envelope_only`, the container at `start_parse_at`.
+    /// A root object has no entry otherwise, the first entry being its first member.
+    pub fn first_entry(&self) -> Option<&FlatJsonValue<V>> {
+        let order = self.document_order();
+        self.json.iter().min_by_key(|entry| order(entry))
+    }
+
+    /// Last entry in document order: the deepest last descendant of the root.
+    pub fn last_entry(&self) -> Option<&FlatJsonValue<V>> {
+        let order = self.document_order();
+        self.json.iter().max_by_key(|entry| order(entry))
+    }
+
+    // positions only order siblings, entries expanded by change_depth having positions of their own parse: an entry is ordered by positions of its ancestors and itself
+    fn document_order(&self) -> impl Fn(&FlatJsonValue<V>) -> Vec<usize> + '_ {
+        let positions: HashMap<&str, usize> = self.json.iter().map(|entry| (entry.pointer.pointer.as_str(), entry.pointer.position)).collect();
+        move |entry| {
+            let pointer = entry.pointer.pointer.as_str();
+            let ancestors = pointer.match_indices('/').map(|(i, _)| &pointer[0..i]);
+            ancestors.chain(std::iter::once(pointer).filter(|pointer| !pointer.is_empty()))
+                .filter_map(|ancestor| positions.get(ancestor).copied())
+                .collect()
+        }
+    }
+
+    /// Entry with the smallest pointer, compared as strings: `/a/10` is before `/a/2`. The root array `""`, when present, is the smallest.
+    pub fn min_pointer(&self) -> Option<&FlatJsonValue<V>> {
+        self.json.iter().min_by(|a, b| a.pointer.pointer.cmp(&b.pointer.pointer))
+    }
+
+    /// Entry with the largest pointer, compared as strings.
+    pub fn max_pointer(&self) -> Option<&FlatJsonValue<V>> {
+        self.json.iter().max_by(|a, b| a.pointer.pointer.cmp(&b.pointer.pointer))
+    }
+
+    /// Object and array entries, including those not parsed because beyond `max_depth`.
+    pub fn iter_containers(&self) -> impl Iterator<Item=&FlatJsonValue<V>> {
+        self.json.iter().filter(|entry| entry.pointer.value_type.is_container())
+    }
+
+    /// Largest and total array lengths and object key counts, to tell whether a document is deep, wide or both. Arrays are measured with `Array(len)` and objects by counting their children,
+    /// the root object, which has no entry of its own, included. Only expanded containers are measured: the ones beyond `max_depth` are not counted,
+    /// and a result parsed with `emit_containers(false)` has no container to measure.
+    pub fn breadth_stats(&self) -> BreadthStats {
+        let root = self.parsing_prefix.as_deref().unwrap_or("");
+        let mut children: HashMap<&str, usize> = HashMap::new();
+        let mut has_root_entry = false;
+        for entry in self.json.iter() {
+            let pointer = entry.pointer.pointer.as_str();
+            // the root array entry is "" whatever the prefix
+            has_root_entry |= pointer == root || pointer.is_empty();
+            if pointer != root {
+                *children.entry(&pointer[0..pointer.rfind('/').unwrap_or(0)]).or_default() += 1;
+            }
+        }
+        let mut stats = BreadthStats::default();
+        if !has_root_entry && children.contains_key(root) {
+            stats.add_object(children[root]);
+        }
+        for entry in self.json.iter() {
+            let measured = children.get(entry.pointer.pointer.as_str()).copied();
+            match entry.pointer.value_type {
+                ValueType::Object(true) => stats.add_object(measured.unwrap_or(0)),
+                // an empty array is Array(1) like an array of one element, arrays beyond max_depth have their raw data
+                ValueType::Array(len) if entry.value.is_none() => stats.add_array(if measured.is_some() { len } else { 0 }),
+                _ => {}
+            }
+        }
+        stats
+    }
+
+    /// Length of the largest expanded array, see [`ParseResult::breadth_stats`].
+    pub fn max_array_len(&self) -> usize {
+        self.breadth_stats().max_array_len
+    }
+
+    /// Longest pointer prefix shared by all entries, at segment granularity, e.g. `/response/data` when everything lives below it: `/a/bc` and `/a/bd` share `/a`, not `/a/b`.
+    /// A pointer is a prefix of itself, so a single entry gives its own pointer. Returns `"/"` when there is no common prefix, e.g. when the root array entry `""` is present.
+    pub fn common_prefix(&self) -> &str {
+        let Some(first) = self.json.first() else {
+            return "/";
+        };
+        let mut prefix = first.pointer.pointer.as_str();
+        for entry in self.json[1..].iter() {
+            let pointer = entry.pointer.pointer.as_str();
+            let mut common = 0;
+            for (a, b) in prefix.split('/').zip(pointer.split('/')).skip(1) {
+                if a != b {
+                    break;
+                }
+                common += 1 + a.len();
+            }
+            prefix = &prefix[..common];
+            if prefix.is_empty() {
+                break;
+            }
+        }
+        if prefix.is_empty() { "/" } else { prefix }
+    }
+
+    /// Entries whose pointer ends with `suffix` at a segment boundary, e.g. `email` or `contact/email` find `/users/0/contact/email` but `email` does not find `/preferred_email`.
+    pub fn find_by_suffix(&self, suffix: &str) -> Vec<&FlatJsonValue<V>> {
+        let suffix = suffix.strip_prefix('/').unwrap_or(suffix);
+        if suffix.is_empty() {
+            return vec![];
+        }
+        self.json.iter().filter(|entry| {
+            entry.pointer.pointer.strip_suffix(suffix).is_some_and(|head| head.ends_with('/'))
+        }).collect()
+    }
+
+    /// Scalar at `pointer` parsed with `FromStr`, e.g. `result.get_or("/timeout", 30)` when reading a configuration. Returns `default` when the pointer is absent,
+    /// its value is `null` or a container, or parsing fails: a mistyped value is silently replaced, use [`ParseResult::try_get`] to tell these cases apart.
+    /// Strings are parsed from their content, so `"30"` gives `30` like `30` does.
+    pub fn get_or<T: FromStr>(&self, pointer: &str, default: T) -> T {
+        self.try_get(pointer).ok().flatten().unwrap_or(default)
+    }
+
+    /// Scalar at `pointer` parsed with `FromStr`: `Ok(None)` when the pointer is absent or its value is `null`,
+    /// `TypeMismatch` with the pointer and the name of `T` when the value is a container or cannot be parsed as `T`.
+    /// The error is a [`TypeMismatch`] rather than a `ParseError`: the input is already parsed, so there is no offset to report, a mismatch being about a value of the result.
+    pub fn try_get<T: FromStr>(&self, pointer: &str) -> Result<Option<T>, TypeMismatch> {
+        let Some(entry) = self.json.iter().find(|entry| entry.pointer.pointer == pointer) else {
+            return Ok(None);
+        };
+        let mismatch = || TypeMismatch { pointer: pointer.to_string(), expected: std::any::type_name::<T>() };
+        match entry.pointer.value_type {
+            ValueType::Null | ValueType::None => Ok(None),
+            value_type if value_type.is_container() => Err(mismatch()),
+            _ => match entry.value.as_ref() {
+                Some(value) => value.as_ref().parse::<T>().map(Some).map_err(|_| mismatch()),
+                None => Ok(None),
+            },
+        }
+    }
+
+    /// Branch on the root value: an array when there is an array entry with the empty pointer, its entries being grouped by element,
+    /// a scalar when the entry with the empty pointer is neither an array nor an object, an object otherwise, the root object entry added by `envelope_only` being dropped.
+    /// A root array parsed with `emit_containers(false)` has no entry of its own, thus is reported as an object.
+    /// Elements are the ones having entries, in index order, `index` being the one of their pointers like for `rows`, e.g. starting at 1 with `array_index_base(1)`:
+    /// no empty element is made up for an index missing from the result, e.g. after `parse_projection` or `retain_pointers`.
+    pub fn dispatch(self) -> RootKind<V> {
+        let root = self.json.iter().position(|entry| entry.pointer.pointer.is_empty());
+        let Some(root) = root else {
+            return RootKind::Object(self.json);
+        };
+        let mut json = self.json;
+        let root = json.remove(root);
+        match root.pointer.value_type {
+            ValueType::Array(_) => {}
+            ValueType::Object(_) => return RootKind::Object(json),
+            _ => return RootKind::Scalar(root),
+        }
+        json.sort_by_key(|entry| entry.pointer.position);
+        let mut elements: BTreeMap<usize, Vec<FlatJsonValue<V>>> = BTreeMap::new();
+        for entry in json {
+            if let Some((index, _)) = array_element_index(&entry.pointer.pointer, "") {
+                elements.entry(index).or_default().push(entry);
+            }
+        }
+        RootKind::Array(elements.into_iter().map(|(index, entries)| JsonArrayEntries { entries, index }).collect())
+    }
+
+    /// Entries of each element of the array at `array_pointer`, `""` being the root array, element itself included, without cloning them.
+    /// Entries of an element must be contiguous in `json`, which is the case in parser output as it is in document order.
+    /// Otherwise, e.g. after `change_depth` which appends expanded children at the end, an element is yielded once per contiguous run of its entries, `dispatch` groups them instead.
+    pub fn rows<'a>(&'a self, array_pointer: &'a str) -> impl Iterator<Item=RowView<'a, V>> + 'a {
+        let json = self.json.as_slice();
+        let element_index = move |i: usize| json.get(i).and_then(|entry| array_element_index(&entry.pointer.pointer, array_pointer)).map(|(index, _)| index);
+        let mut start = 0;
+        std::iter::from_fn(move || {
+            while start < json.len() && element_index(start).is_none() {
+                start += 1;
+            }
+            let index = element_index(start)?;
+            let mut end = start + 1;
+            while element_index(end) == Some(index) {
+                end += 1;
+            }
+            let row = RowView { entries: &json[start..end], index };
+            start = end;
+            Some(row)
+        })
+    }
+
+    /// Copy of this result without entries deeper than `max_depth`, e.g. to render the first levels of a tree quickly. Entries are filtered, nothing is parsed nor serialized.
+    /// Containers at `max_depth` are kept: objects having raw data are marked as not parsed so `change_depth` can expand them again, arrays have no raw data once parsed,
+    /// so their elements are lost unless the array was already kept as raw data.
+    pub fn prune_to_depth(&self, max_depth: u8) -> ParseResult<V> {
+        let json = self.json.iter().filter(|entry| entry.pointer.depth <= max_depth).cloned().map(|mut entry| {
+            if entry.pointer.depth == max_depth && entry.value.is_some() && matches!(entry.pointer.value_type, ValueType::Object(true)) {
+                entry.pointer.value_type = ValueType::Object(false);
+            }
+            entry
+        }).collect();
+        ParseResult {
+            json,
+            max_json_depth: self.max_json_depth.min(max_depth as usize),
+            parsing_max_depth: self.parsing_max_depth.min(max_depth.saturating_sub(self.depth_after_start_at)),
+            started_parsing_at: self.started_parsing_at.clone(),
+            parsing_prefix: self.parsing_prefix.clone(),
+            depth_after_start_at: self.depth_after_start_at,
+            source_span: self.source_span.clone(),
+        }
+    }
+
+    /// Copy of this result where elements of the array at `array_pointer` are sorted by the value of their `child_key` member, e.g. `/items` elements by `id`.
+    /// Numbers are compared numerically, other values as text, elements without the child are moved last, the sort is stable.
+    /// Pointers of every element descendant are rewritten with their new index, as well as positions so the serializer emits elements in the new order.
+    /// This is O(n log n) and the array must be fully expanded: children of elements kept as raw data can't be reached. The raw data of the array itself, if kept, is stale.
+    pub fn sort_array_by_key(&self, array_pointer: &str, child_key: &str) -> ParseResult<V> {
+        let mut result = self.clone();
+        let child_key = child_key.strip_prefix('/').unwrap_or(child_key);
+        let mut element_of: Vec<Option<usize>> = Vec::with_capacity(result.json.len());
+        let mut keys: Vec<Option<&FlatJsonValue<V>>> = Vec::new();
+        for entry in self.json.iter() {
+            let element = array_element_index(&entry.pointer.pointer, array_pointer);
+            if let Some((index, index_end)) = element {
+                if keys.len() <= index {
+                    keys.resize(index + 1, None);
+                }
+                if entry.pointer.pointer[index_end..].strip_prefix('/') == Some(child_key) {
+                    keys[index] = Some(entry);
+                }
+            }
+            element_of.push(element.map(|(index, _)| index));
+        }
+        let mut order: Vec<usize> = (0..keys.len()).collect();
+        order.sort_by(|a, b| compare_sort_keys(keys[*a], keys[*b]));
+        let mut new_indexes = vec![0; keys.len()];
+        for (new_index, old_index) in order.into_iter().enumerate() {
+            new_indexes[old_index] = new_index;
+        }
+        reorder_array_elements(&mut result.json, array_pointer, &element_of, &new_indexes);
+        result
+    }
+
+    /// Keep only entries at `keep` pointers, where `*` matches any array index like with `parse_projection`, e.g. `/users/*/email`, along with descendants of kept containers:
+    /// the analog of `parse_projection` for an already flattened result, e.g. to export a subset of it. With `keep_ancestors`, container entries above kept entries are kept too,
+    /// so that the result can be serialized, the root array `""` included. Entries are kept in their order.
+    pub fn retain_pointers(&mut self, keep: &[&str], keep_ancestors: bool) {
+        let patterns: Vec<Vec<&str>> = keep.iter()
+            .map(|pointer| pointer.strip_prefix('/').unwrap_or(pointer))
+            .map(|pointer| if pointer.is_empty() { vec![] } else { pointer.split('/').collect() })
+            .collect();
+        let matches = |pointer: &str| {
+            let segments: Vec<&str> = pointer.split('/').skip(1).collect();
+            patterns.iter().any(|pattern| pattern.len() <= segments.len() && pattern.iter().zip(segments.iter())
+                .all(|(expected, segment)| expected == segment || (*expected == "*" && segment.parse::<usize>().is_ok())))
+        };
+        let kept: Vec<bool> = self.json.iter().map(|entry| matches(&entry.pointer.pointer)).collect();
+        let mut ancestors: HashSet<String> = HashSet::new();
+        if keep_ancestors {
+            for entry in self.json.iter().zip(kept.iter()).filter(|(_, kept)| **kept).map(|(entry, _)| entry) {
+                let pointer = entry.pointer.pointer.as_str();
+                for (i, _) in pointer.match_indices('/') {
+                    if !ancestors.contains(&pointer[0..i]) {
+                        ancestors.insert(pointer[0..i].to_string());
+                    }
+                }
+            }
+        }
+        let mut kept = kept.into_iter();
+        self.json.retain(|entry| kept.next().unwrap() || ancestors.contains(&entry.pointer.pointer));
+    }
+
+    /// Renumber elements of the array at `array_pointer` to be contiguous from 0, e.g. after entries were removed or inserted by hand, keeping them ordered by index,
+    /// elements sharing an index being kept in entries order: an element pushed at `/items/1` comes after the existing `/items/1`. Entries of descendants are rewritten,
+    /// being assigned to the last element entry with their index preceding them, and the array length is updated.
+    pub fn normalize_array_indices(&mut self, array_pointer: &str) {
+        let mut element_of: Vec<Option<usize>> = Vec::with_capacity(self.json.len());
+        // (index, element) of elements in entries order, and the last element having an index
+        let mut elements: Vec<(usize, usize)> = Vec::new();
+        let mut last_element_at: HashMap<usize, usize> = HashMap::new();
+        for entry in self.json.iter() {
+            let element = array_element_index(&entry.pointer.pointer, array_pointer).map(|(index, index_end)| {
+                match last_element_at.get(&index) {
+                    Some(element) if index_end < entry.pointer.pointer.len() => *element,
+                    _ => {
+                        let element = elements.len();
+                        elements.push((index, element));
+                        last_element_at.insert(index, element);
+                        element
+                    }
+                }
+            });
+            element_of.push(element);
+        }
+        elements.sort();
+        let mut new_indexes = vec![0; elements.len()];
+        for (new_index, (_, element)) in elements.iter().enumerate() {
+            new_indexes[*element] = new_index;
+        }
+        reorder_array_elements(&mut self.json, array_pointer, &element_of, &new_indexes);
+        if let Some(array) = self.json.iter_mut().find(|entry| entry.pointer.pointer == array_pointer && matches!(entry.pointer.value_type, ValueType::Array(_))) {
+            array.pointer.value_type = ValueType::Array(elements.len().max(1));
+        }
+    }
+}
+
+impl<V: Debug + Clone + AsRef<str> + GetBytes> ParseResult<V> {
+    /// Check entries of a result built by hand are consistent before serializing it: pointers are `""` or start with `/` and are unique,
+    /// the parent of an entry, when present, is an object or an array, children of arrays have an index as last segment,
+    /// and depth of an entry is its parent one plus 1 (root array elements having the depth of the root array) and agrees with its pointer, see [`PointerKey::computed_depth`].
+    /// Roles, when annotated, agree with the type of the parent.
+    /// Pointers being unique, an array has a single element per index, which also holds for indexes written differently like `2` and `02`.
+    /// Returns a message describing the first inconsistency.
+    pub fn validate_pointers(&self) -> Result<(), String> {
+        let mut entries: HashMap<&str, &PointerKey> = HashMap::with_capacity(self.json.len());
+        for entry in self.json.iter() {
+            let pointer = entry.pointer.pointer.as_str();
+            if !pointer.is_empty() && !pointer.starts_with('/') {
+                return Err(format!("Pointer {:?} should start with '/'", pointer));
+            }
+            if entries.insert(pointer, &entry.pointer).is_some() {
+                return Err(format!("Pointer {:?} is duplicated", pointer));
+            }
+        }
+        let mut depth_offset: Option<(i32, &str)> = None;
+        let mut array_indexes: HashSet<(&str, usize)> = HashSet::new();
+        for entry in self.json.iter() {
+            let pointer = entry.pointer.pointer.as_str();
+            let Some(separator) = pointer.rfind('/') else {
+                continue;
+            };
+            match depth_offset {
+                Some((offset, first)) if entry.pointer.depth_offset() != offset => {
+                    return Err(format!("Pointer {:?} has depth {} while {} is expected from its {} segments, like for {:?}",
+                                       pointer, entry.pointer.depth, entry.pointer.computed_depth() as i32 + offset, entry.pointer.computed_depth(), first));
+                }
+                Some(_) => {}
+                None => depth_offset = Some((entry.pointer.depth_offset(), pointer)),
+            }
+            let Some(parent) = entries.get(&pointer[0..separator]) else {
+                continue;
+            };
+            let expected_depth = match parent.value_type {
+                ValueType::Array(_) => {
+                    if entry.pointer.role.is_some_and(|role| role != PointerRole::ArrayElement) {
+                        return Err(format!("Pointer {:?} is a child of array {:?} but has role {:?}", pointer, parent.pointer, entry.pointer.role.unwrap()));
+                    }
+                    let Ok(index) = pointer[separator + 1..].parse::<usize>() else {
+                        return Err(format!("Pointer {:?} is a child of array {:?} but its last segment is not an index", pointer, parent.pointer));
+                    };
+                    // e.g. `/items/2` and `/items/02`
+                    if !array_indexes.insert((parent.pointer.as_str(), index)) {
+                        return Err(format!("Pointer {:?} has index {} of another element of array {:?}, see ParseResult::normalize_array_indices", pointer, index, parent.pointer));
+                    }
+                    if parent.pointer.is_empty() { parent.depth } else { parent.depth + 1 }
+                }
+                ValueType::Object(_) => {
+                    if entry.pointer.role.is_some_and(|role| role != PointerRole::ObjectMember) {
+                        return Err(format!("Pointer {:?} is a child of object {:?} but has role {:?}", pointer, parent.pointer, entry.pointer.role.unwrap()));
+                    }
+                    parent.depth + 1
+                }
+                value_type => return Err(format!("Pointer {:?} is a child of {:?} which is {:?}, not an object nor an array", pointer, parent.pointer, value_type)),
+            };
+            if entry.pointer.depth != expected_depth {
+                return Err(format!("Pointer {:?} has depth {} while {} is expected from its parent {:?}", pointer, entry.pointer.depth, expected_depth, parent.pointer));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Build a result from entries, e.g. to serialize generated data without parsing text. `max_json_depth` and `parsing_max_depth` are the deepest entry depth.
+impl<V: Debug + Clone + AsRef<str> + GetBytes> FromIterator<FlatJsonValue<V>> for ParseResult<V> {
+    fn from_iter<T: IntoIterator<Item=FlatJsonValue<V>>>(iter: T) -> Self {
+        let mut result = ParseResult {
+            json: Vec::new(),
+            max_json_depth: 0,
+            parsing_max_depth: 0,
+            started_parsing_at: None,
+            parsing_prefix: None,
+            depth_after_start_at: 0,
+            source_span: 0..0,
+        };
+        result.extend(iter);
+        result
+    }
+}
+
+impl<V: Debug + Clone + AsRef<str> + GetBytes> FromIterator<(PointerKey, Option<V>)> for ParseResult<V> {
+    fn from_iter<T: IntoIterator<Item=(PointerKey, Option<V>)>>(iter: T) -> Self {
+        iter.into_iter().map(|(pointer, value)| FlatJsonValue { pointer, value }).collect()
+    }
+}
+
+impl<V: Debug + Clone + AsRef<str> + GetBytes> Extend<FlatJsonValue<V>> for ParseResult<V> {
+    fn extend<T: IntoIterator<Item=FlatJsonValue<V>>>(&mut self, iter: T) {
+        for entry in iter {
+            if self.parsing_max_depth < entry.pointer.depth {
+                self.parsing_max_depth = entry.pointer.depth;
+            }
+            self.max_json_depth = self.max_json_depth.max(entry.pointer.depth as usize);
+            self.json.push(entry);
+        }
+    }
+}
+
+impl<V: Debug + Clone + AsRef<str> + GetBytes> Extend<(PointerKey, Option<V>)> for ParseResult<V> {
+    fn extend<T: IntoIterator<Item=(PointerKey, Option<V>)>>(&mut self, iter: T) {
+        self.extend(iter.into_iter().map(|(pointer, value)| FlatJsonValue { pointer, value }));
+    }
+}
+
+fn compare_sort_keys<V: Debug + Clone + AsRef<str> + GetBytes>(a: Option<&FlatJsonValue<V>>, b: Option<&FlatJsonValue<V>>) -> Ordering {
+    match (a.and_then(|a| a.value.as_ref()), b.and_then(|b| b.value.as_ref())) {
+        (Some(a_value), Some(b_value)) => {
+            if matches!(a.unwrap().pointer.value_type, ValueType::Number) && matches!(b.unwrap().pointer.value_type, ValueType::Number) {
+                if let (Ok(a_number), Ok(b_number)) = (a_value.as_ref().parse::<f64>(), b_value.as_ref().parse::<f64>()) {
+                    return a_number.partial_cmp(&b_number).unwrap_or(Ordering::Equal);
+                }
+            }
+            a_value.as_ref().cmp(b_value.as_ref())
+        }
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// Remove entries of parsed objects having a single member, which is not an array element, joining the key of the object with the one of its member, see [`ParseOptions::fold_single_key_objects`].
+fn fold_single_key_objects<V: Debug + Clone + AsRef<str> + GetBytes>(json: &mut Vec<FlatJsonValue<V>>, separator: &str) {
+    let folded: HashSet<String> = {
+        let value_types: HashMap<&str, ValueType> = json.iter().map(|entry| (entry.pointer.pointer.as_str(), entry.pointer.value_type)).collect();
+        let mut children: HashMap<&str, usize> = HashMap::new();
+        for entry in json.iter().filter(|entry| !entry.pointer.pointer.is_empty()) {
+            let pointer = entry.pointer.pointer.as_str();
+            *children.entry(&pointer[0..pointer.rfind('/').unwrap_or(0)]).or_default() += 1;
+        }
+        json.iter()
+            .map(|entry| entry.pointer.pointer.as_str())
+            .filter(|pointer| !pointer.is_empty() && matches!(value_types[pointer], ValueType::Object(true)) && children.get(pointer) == Some(&1))
+            .filter(|pointer| !matches!(value_types.get(&pointer[0..pointer.rfind('/').unwrap_or(0)]), Some(ValueType::Array(_))))
+            .map(str::to_string)
+            .collect()
+    };
+    if folded.is_empty() {
+        return;
+    }
+    json.retain(|entry| !folded.contains(&entry.pointer.pointer));
+    for entry in json.iter_mut() {
+        let pointer = &entry.pointer.pointer;
+        let mut joins = 0;
+        let mut rebuilt = String::with_capacity(pointer.len());
+        let mut segment_start = 0;
+        for (i, _) in pointer.match_indices('/').skip(1) {
+            rebuilt.push_str(&pointer[segment_start..i]);
+            if folded.contains(&pointer[0..i]) {
+                rebuilt.push_str(separator);
+                joins += 1;
+                segment_start = i + 1;
+            } else {
+                segment_start = i;
+            }
+        }
+        if joins > 0 {
+            rebuilt.push_str(&pointer[segment_start..]);
+            entry.pointer.pointer = rebuilt;
+            // depth follows the number of segments
+            if !separator.contains('/') {
+                entry.pointer.depth -= joins;
+            }
+        }
+    }
+}
+
+/// Value of an owned result, see [`JSONParser::parse_owned`], for `value` of the entry at `pointer` of a result borrowing the input.
+fn owned_value<'a>(options: &ParseOptions, pointer: &PointerKey, value: &'a str, canonical_numbers: &'a HashMap<usize, String>) -> Cow<'a, str> {
+    let value = match pointer.value_type {
+        ValueType::Number => canonical_numbers.get(&(value.as_ptr() as usize)).map_or(Cow::Borrowed(value), |canonical| Cow::Borrowed(canonical.as_str())),
+        ValueType::String if !options.deny_unknown_escape => drop_invalid_escapes(value),
+        _ => Cow::Borrowed(value),
+    };
+    if pointer.original_len.is_some() {
+        return Cow::Owned(concat_string!(value, "…"));
+    }
+    value
+}
+
+/// Index of the element of the array at `array_pointer` containing `pointer`, along with the end offset of the index segment in `pointer`.
+fn array_element_index(pointer: &str, array_pointer: &str) -> Option<(usize, usize)> {
+    let remainder = pointer.strip_prefix(array_pointer)?.strip_prefix('/')?;
+    let segment_len = remainder.find('/').unwrap_or(remainder.len());
+    let index = remainder[0..segment_len].parse::<usize>().ok()?;
+    Some((index, array_pointer.len() + 1 + segment_len))
+}
+
+/// Move elements of the array at `array_pointer`: entries having `element_of[i] = Some(element)` get `new_indexes[element]` as index segment.
+/// Positions of moved entries are redistributed so that document order follows the new indexes.
+fn reorder_array_elements<V: Debug + Clone + AsRef<str> + GetBytes>(json: &mut [FlatJsonValue<V>], array_pointer: &str, element_of: &[Option<usize>], new_indexes: &[usize]) {
+    let mut moved: Vec<usize> = (0..json.len()).filter(|i| element_of[*i].is_some()).collect();
+    let mut positions: Vec<usize> = moved.iter().map(|i| json[*i].pointer.position).collect();
+    positions.sort_unstable();
+    moved.sort_by_key(|i| (new_indexes[element_of[*i].unwrap()], json[*i].pointer.position));
+    for (i, position) in moved.into_iter().zip(positions) {
+        let new_index = new_indexes[element_of[i].unwrap()];
+        let pointer = &mut json[i].pointer;
+        let (_, index_end) = array_element_index(&pointer.pointer, array_pointer).unwrap();
+        pointer.pointer = concat_string!(array_pointer, "/", new_index.to_string(), &pointer.pointer[index_end..]);
+        if array_pointer.is_empty() {
+            pointer.index = new_index;
+        }
+        pointer.position = position;
+    }
+}
+
+pub type ParseResultOwned = ParseResult<String>;
+
+/// Entry of [`InternedPointers`], its pointer being replaced by the id given by [`InternedPointers::resolve`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct InternedEntry<V> {
+    pub id: u32,
+    pub value_type: ValueType,
+    pub value: Option<V>,
+}
+
+/// Entries whose pointers are interned as `u32` ids, see [`ParseResult::with_interned_pointers`].
+#[derive(Debug, Clone)]
+pub struct InternedPointers<V> {
+    /// In entries order, entries sharing a pointer, e.g. duplicated keys, sharing an id.
+    pub entries: Vec<InternedEntry<V>>,
+    pointers: Vec<String>,
+    ids: HashMap<String, u32>,
+}
+
+impl<V> InternedPointers<V> {
+    /// Pointer of `id`, ids being dense from 0 in order of first appearance. Panics when `id` was not given by this representation.
+    pub fn resolve(&self, id: u32) -> &str {
+        &self.pointers[id as usize]
+    }
+
+    pub fn id_of(&self, pointer: &str) -> Option<u32> {
+        self.ids.get(pointer).copied()
+    }
+
+    /// First entry having `id`, entries being compared by integer only.
+    pub fn find(&self, id: u32) -> Option<&InternedEntry<V>> {
+        self.entries.iter().find(|entry| entry.id == id)
+    }
+
+    /// Distinct pointers.
+    pub fn pointer_count(&self) -> usize {
+        self.pointers.len()
+    }
+}
+
+impl TryFrom<&str> for ParseResultOwned {
+    type Error = ParseError;
+
+    /// Parse `input` with default options into an owned result.
+    fn try_from(input: &str) -> Result<Self, Self::Error> {
+        JSONParser::parse(input, ParseOptions::default()).map(|res| res.to_owned())
+    }
 }
 
 impl ParseResult<String> {
@@ -290,6 +1458,7 @@ impl ParseResult<String> {
             started_parsing_at: self.started_parsing_at.clone(),
             parsing_prefix: self.parsing_prefix.clone(),
             depth_after_start_at: self.depth_after_start_at,
+            source_span: self.source_span.clone(),
         }
     }
 
@@ -297,8 +1466,86 @@ impl ParseResult<String> {
         self
     }
 
+    /// Concatenate top-level arrays, e.g. paginated responses, into one: elements of each result get indexes following the ones of the previous results,
+    /// their pointers and `index` being rewritten, `index` of elements of the first result being set too, and positions are shifted so that document order follows results order. The root array entry is the one of the first result,
+    /// with the total length, other fields too. Elements are not compared, schemas may differ. Results without a root array entry, e.g. parsed with `emit_containers(false)`,
+    /// are taken as arrays too, only their entries pointing into an element being kept. Without results, this is an empty array.
+    pub fn merge_arrays(results: Vec<ParseResultOwned>) -> ParseResultOwned {
+        let mut results = results.into_iter();
+        let Some(mut merged) = results.next() else {
+            return vec![(PointerKey::from_pointer(String::new(), ValueType::Array(1), 1, 0), None)].into_iter().collect();
+        };
+        let element_count = |json: &[FlatJsonValue<String>]| json.iter().filter_map(|entry| array_element_index(&entry.pointer.pointer, "")).map(|(index, _)| index + 1).max().unwrap_or(0);
+        for entry in merged.json.iter_mut() {
+            if let Some((index, _)) = array_element_index(&entry.pointer.pointer, "") {
+                entry.pointer.index = index;
+            }
+        }
+        let mut len = element_count(&merged.json);
+        let mut last_position = merged.json.iter().map(|entry| entry.pointer.position).max().unwrap_or(0);
+        for result in results {
+            let result_len = element_count(&result.json);
+            let result_last_position = result.json.iter().map(|entry| entry.pointer.position).max().unwrap_or(0);
+            merged.max_json_depth = merged.max_json_depth.max(result.max_json_depth);
+            merged.json.extend(result.json.into_iter().filter_map(|mut entry| {
+                let (index, index_end) = array_element_index(&entry.pointer.pointer, "")?;
+                entry.pointer.pointer = concat_string!("/", (index + len).to_string(), &entry.pointer.pointer[index_end..]);
+                entry.pointer.index = index + len;
+                entry.pointer.position += last_position;
+                Some(entry)
+            }));
+            len += result_len;
+            last_position += result_last_position;
+        }
+        if let Some(root) = merged.json.iter_mut().find(|entry| entry.pointer.pointer.is_empty()) {
+            root.pointer.value_type = ValueType::Array(len.max(1));
+        }
+        merged
+    }
+
+    /// Release excess capacity left by edits, e.g. before caching a result for a long time: the entries vector as well as each pointer and value string are shrunk to fit.
+    pub fn compact(mut self) -> ParseResultOwned {
+        for entry in self.json.iter_mut() {
+            entry.pointer.pointer.shrink_to_fit();
+            if let Some(ref mut value) = entry.value {
+                value.shrink_to_fit();
+            }
+        }
+        self.json.shrink_to_fit();
+        self
+    }
+
+    /// Rewrite every leaf value in place with `f`, e.g. to trim, lowercase or mask values: `f` gets the key of the entry and its value, and returns the new value
+    /// along with the new `value_type` of the entry, if it changes. Returning no type and no value for a leaf which had a value turns it into a `Null`.
+    /// Object and array entries are left untouched: their raw data, when kept, still holds the original text and is stale after this call.
+    pub fn map_values(&mut self, f: impl Fn(&PointerKey, Option<String>) -> (Option<String>, Option<ValueType>)) {
+        for entry in self.json.iter_mut() {
+            if matches!(entry.pointer.value_type, ValueType::Array(_) | ValueType::Object(_)) {
+                continue;
+            }
+            let had_value = entry.value.is_some();
+            let (value, value_type) = f(&entry.pointer, entry.value.take());
+            entry.value = value;
+            match value_type {
+                Some(value_type) => entry.pointer.value_type = value_type,
+                None if had_value && entry.value.is_none() => entry.pointer.value_type = ValueType::Null,
+                None => {}
+            }
+        }
+    }
+
 }
 impl ParseResult<&str> {
+    /// Release excess capacity of the entries vector and of pointer strings.
+    /// Values still borrow the whole input: use `to_owned().compact()` to build a tight result which does not retain it.
+    pub fn compact(mut self) -> Self {
+        for entry in self.json.iter_mut() {
+            entry.pointer.pointer.shrink_to_fit();
+        }
+        self.json.shrink_to_fit();
+        self
+    }
+
     pub fn clone_except_json(&self) -> Self {
         Self {
             json: Default::default(),
@@ -307,8 +1554,18 @@ impl ParseResult<&str> {
             started_parsing_at: self.started_parsing_at.clone(),
             parsing_prefix: self.parsing_prefix.clone(),
             depth_after_start_at: self.depth_after_start_at,
+            source_span: self.source_span.clone(),
         }
     }
+    // owned copy having the values of `parse_owned`
+    fn into_owned_with(mut self, options: &ParseOptions, canonical_numbers: &HashMap<usize, String>) -> ParseResult<String> {
+        let json = std::mem::take(&mut self.json).into_iter().map(|entry| FlatJsonValue {
+            value: entry.value.map(|value| owned_value(options, &entry.pointer, value, canonical_numbers).into_owned()),
+            pointer: entry.pointer,
+        }).collect();
+        ParseResult { json, ..self.to_owned() }
+    }
+
     pub fn to_owned(self) -> ParseResult<String> {
         let mut transformed_vec: Vec<FlatJsonValue<String>> = Vec::with_capacity(self.json.len());
 
@@ -322,6 +1579,7 @@ impl ParseResult<&str> {
             started_parsing_at: self.started_parsing_at.clone(),
             parsing_prefix: self.parsing_prefix.clone(),
             depth_after_start_at: self.depth_after_start_at,
+            source_span: self.source_span.clone(),
         }
     }
 
@@ -329,18 +1587,122 @@ impl ParseResult<&str> {
 
 
 impl JSONParser {
-    pub fn parse(input: &str, options: ParseOptions) -> Result<ParseResult<&str>, String> {
+    pub fn parse(input: &str, options: ParseOptions) -> Result<ParseResult<&str>, ParseError> {
         JSONParser::parse_bytes(input.as_bytes(), options)
     }
-    pub fn parse_bytes(input: &[u8], options: ParseOptions) -> Result<ParseResult<&str>, String> {
-        let mut lexer = Lexer::new(input);
+    pub fn parse_bytes(input: &[u8], options: ParseOptions) -> Result<ParseResult<&str>, ParseError> {
+        JSONParser::parse_with_canonical_numbers(input, &options).map(|(res, _)| res)
+    }
+
+    fn parse_with_canonical_numbers<'json>(input: &'json [u8], options: &ParseOptions) -> Result<(ParseResult<&'json str>, HashMap<usize, String>), ParseError> {
+        options.validate().map_err(|reason| ParseError::InvalidOptions { reason, offset: 0 })?;
+        let mut lexer = Lexer::new(input).strict_numbers(options.strict_numbers);
+        let mut parser = Parser::new(&mut lexer);
+        let res = parser.parse(options, options.start_depth);
+        let canonical_numbers = std::mem::take(&mut parser.canonical_numbers);
+        let mut res = lexer.utf8_checked(res)?;
+        if let Some(ref progress) = options.progress {
+            progress(input.len(), input.len());
+        }
+        if options.fold_single_key_objects {
+            fold_single_key_objects(&mut res.json, &options.fold_separator);
+        }
+        Ok((res, canonical_numbers))
+    }
+
+    /// Like `parse`, values being owned, so that numbers are the canonical ones returned by the recognizer of `ParseOptions::number_format`
+    /// and string values truncated by `ParseOptions::truncate_string_values` end with the `…` marker.
+    pub fn parse_owned(input: &str, options: ParseOptions) -> Result<ParseResultOwned, ParseError> {
+        JSONParser::parse_with_canonical_numbers(input.as_bytes(), &options).map(|(res, canonical_numbers)| res.into_owned_with(&options, &canonical_numbers))
+    }
+
+    /// Parse `input`, a standalone value, as the element at `index` of the array at `prefix`, e.g. a line of a line delimited export as element 7 of `/data`.
+    /// Pointers, depths and `index` of entries are the ones they would have if the element was parsed within its array. The `prefix` option is ignored, `index` is 0-based whatever `array_index_base` is.
+    pub fn parse_as_element<'json>(input: &'json str, prefix: &str, index: usize, options: ParseOptions) -> Result<ParseResult<&'json str>, ParseError> {
+        let prefix = if prefix == "/" { "" } else { prefix };
+        let pointer = concat_string!(prefix, "/", (index + options.array_index_base).to_string());
+        let depth = options.start_depth + prefix.matches('/').count() as u8;
+        let mut lexer = Lexer::new(input.as_bytes()).strict_numbers(options.strict_numbers);
         let mut parser = Parser::new(&mut lexer);
-        parser.parse(&options, options.start_depth)
+        let mut res = parser.parse_value_at(&options, &pointer, depth)?;
+        for entry in res.json.iter_mut() {
+            entry.pointer.index = index + options.array_index_base;
+        }
+        Ok(res)
+    }
+
+    /// Lazily parse a JSON text sequence (RFC 7464), records separated by `RS` (`0x1E`); blank records are skipped and error offsets are relative to the record.
+    pub fn parse_json_seq<'json>(input: &'json str, options: ParseOptions) -> impl Iterator<Item=Result<ParseResult<&'json str>, ParseError>> + 'json {
+        input.split('\x1e')
+            .filter(|record| !record.trim_ascii().is_empty())
+            .map(move |record| JSONParser::parse(record, options.clone()))
+    }
+
+    /// Append `elements`, standalone values, to the array at `prefix` of `result` (parsed with its array expanded and the same `options`); on error `result` is left untouched.
+    pub fn append_array_elements<'json>(result: &mut ParseResult<&'json str>, elements: &[&'json str], prefix: &str, options: ParseOptions) -> Result<(), ParseError> {
+        let prefix = if prefix == "/" { "" } else { prefix };
+        let mut index = 0;
+        let mut last_position = 0;
+        for entry in result.json.iter() {
+            if let Some((i, index_end)) = array_element_index(&entry.pointer.pointer, prefix) {
+                if index_end == entry.pointer.pointer.len() {
+                    index = index.max((i + 1).saturating_sub(options.array_index_base));
+                }
+            }
+            last_position = last_position.max(entry.pointer.position);
+        }
+        let mut appended = Vec::new();
+        let mut max_json_depth = result.max_json_depth;
+        for element in elements {
+            let res = JSONParser::parse_as_element(element, prefix, index, options.clone())?;
+            max_json_depth = max_json_depth.max(res.max_json_depth);
+            let element_last_position = res.json.iter().map(|entry| entry.pointer.position).max().unwrap_or(0) + last_position;
+            appended.extend(res.json.into_iter().map(|mut entry| {
+                entry.pointer.position += last_position;
+                entry
+            }));
+            last_position = element_last_position;
+            index += 1;
+        }
+        result.max_json_depth = max_json_depth;
+        result.json.extend(appended);
+        if !elements.is_empty() {
+            if let Some(array) = result.json.iter_mut().find(|entry| entry.pointer.pointer == prefix && matches!(entry.pointer.value_type, ValueType::Array(_))) {
+                array.pointer.value_type = ValueType::Array(index);
+            }
+        }
+        Ok(())
     }
 
+    /// Heuristically find the pointer of the array holding the actual data of a wrapped response, e.g. `/data` for `{"status":"ok","data":[...]}`.
+    /// The primary array is the one with the most elements among arrays at depth <= 2, the root array (pointer `""`) included; ties are resolved in document order.
+    pub fn find_primary_array(input: &str) -> Option<String> {
+        const PRIMARY_ARRAY_MAX_DEPTH: u8 = 2;
+        // parse one level deeper than candidates so their elements are counted
+        let options = ParseOptions::default().max_depth(PRIMARY_ARRAY_MAX_DEPTH + 1).keep_object_raw_data(false);
+        let result = JSONParser::parse(input, options).ok()?;
+        let pointers: HashSet<&str> = result.json.iter().map(|entry| entry.pointer.pointer.as_str()).collect();
+        let mut primary: Option<(&str, usize)> = None;
+        for entry in result.json.iter() {
+            if entry.pointer.depth > PRIMARY_ARRAY_MAX_DEPTH {
+                continue;
+            }
+            if let ValueType::Array(mut len) = entry.pointer.value_type {
+                // empty arrays are reported with a length of 1
+                if len == 1 && !pointers.contains(concat_string!(entry.pointer.pointer, "/0").as_str()) {
+                    len = 0;
+                }
+                if primary.is_none() || primary.unwrap().1 < len {
+                    primary = Some((entry.pointer.pointer.as_str(), len));
+                }
+            }
+        }
+        primary.map(|(pointer, _)| pointer.to_string())
+    }
 
-    change_depth!(&'json str, change_depth, |r: ParseResult<&'json str>| r);
-    change_depth!(String, change_depth_owned, |r: ParseResult<&str>| r.to_owned());
+
+    change_depth!(&'json str, change_depth, |r: ParseResult<&'json str>, _: &ParseOptions, _: &HashMap<usize, String>| r);
+    change_depth!(String, change_depth_owned, |r: ParseResult<&str>, options: &ParseOptions, canonical_numbers: &HashMap<usize, String>| r.into_owned_with(options, canonical_numbers));
 
 
     pub fn serialize<'a>(data: &mut Vec<FlatJsonValue<&'a str>>) -> Value<&'a str> {
@@ -362,4 +1724,657 @@ pub fn string_from_bytes(bytes: &[u8]) -> Option<&str> {
     #[cfg(not(feature = "simdutf8"))]{
         std::str::from_utf8(bytes).ok()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::{ParseError, TypeMismatch};
+    #[cfg(feature = "indexmap")]
+    use crate::serializer::serialize_to_json;
+    use crate::{BreadthStats, FlatJsonValue, InternedEntry, JSONParser, ParseOptions, ParseResult, ParseResultOwned, PointerKey, RootKind, RowView, ValueType};
+
+    #[test]
+    fn find_primary_array() {
+        let json = r#"{"status": "ok", "errors": [], "meta": {"tags": ["a", "b"]}, "data": [{"id": 1, "values": [1, 2, 3, 4]}, {"id": 2}, {"id": 3}]}"#;
+        assert_eq!(JSONParser::find_primary_array(json), Some("/data".to_string()));
+        let json = r#"{"status": "ok", "errors": [], "response": {"items": [1, 2, 3], "tags": ["a"]}}"#;
+        assert_eq!(JSONParser::find_primary_array(json), Some("/response/items".to_string()));
+        assert_eq!(JSONParser::find_primary_array("[1, 2]"), Some("".to_string()));
+        assert_eq!(JSONParser::find_primary_array(r#"{"a": {"b": {"c": [1, 2]}}}"#), None);
+        assert_eq!(JSONParser::find_primary_array("{"), None);
+    }
+
+    #[test]
+    fn relative_to() {
+        let pointer = PointerKey::from_pointer("/skills/0/name".to_string(), ValueType::String, 3, 0);
+        assert_eq!(pointer.relative_to("/skills"), Some("0/name"));
+        assert_eq!(pointer.relative_to("/skills/0"), Some("name"));
+        assert_eq!(pointer.relative_to("/skills/0/name"), Some(""));
+        assert_eq!(pointer.relative_to(""), Some("skills/0/name"));
+        assert_eq!(pointer.relative_to("/"), Some("skills/0/name"));
+        assert_eq!(pointer.relative_to("/skill"), None);
+        assert_eq!(pointer.relative_to("/skills/0/na"), None);
+        assert_eq!(pointer.relative_to("/other"), None);
+    }
+
+    #[test]
+    fn span_in_original_input() {
+        let json = "\u{feff}{\"a\": \"line\\nbreak\", \"b\": {\"c\": 12}}";
+        let mut res = JSONParser::parse(json, ParseOptions::default().max_depth(1)).unwrap();
+        assert_eq!(res.json[0].pointer.pointer, "/a");
+        let span = res.json[0].span_in(json).unwrap();
+        assert_eq!(span, 10..21);
+        assert_eq!(&json[span], r"line\nbreak");
+
+        JSONParser::change_depth(&mut res, ParseOptions::default().max_depth(2)).unwrap();
+        let c = res.json.iter().find(|e| e.pointer.pointer == "/b/c").unwrap();
+        assert_eq!(&json[c.span_in(json).unwrap()], "12");
+
+        let other = String::from(json);
+        assert_eq!(res.json[0].span_in(&other), None);
+    }
+
+    #[test]
+    fn subtract() {
+        let previous = JSONParser::parse(r#"{"id": 1, "name": "Bash", "flags": {"a": true}}"#, ParseOptions::default()).unwrap();
+        let current = JSONParser::parse(r#"{"id": 2, "name": "Bash", "flags": {"a": true, "b": false}, "tags": ["x"]}"#, ParseOptions::default()).unwrap().to_owned();
+        let added: Vec<&str> = ParseResult::subtract(&current, &previous).iter().map(|entry| entry.pointer.pointer.as_str()).collect();
+        assert_eq!(added, vec!["/flags/b", "/tags", "/tags/0"]);
+        assert!(ParseResult::subtract(&previous, &current).is_empty());
+    }
+
+    #[test]
+    fn find_by_suffix() {
+        let json = r#"{"email": "a@b.c", "users": [{"contact": {"email": "d@e.f"}, "preferred_email": "x"}, {"email": null}]}"#;
+        let res = JSONParser::parse(json, ParseOptions::default()).unwrap();
+        let pointers = |suffix: &str| res.find_by_suffix(suffix).iter().map(|entry| entry.pointer.pointer.as_str()).collect::<Vec<&str>>();
+        assert_eq!(pointers("email"), vec!["/email", "/users/0/contact/email", "/users/1/email"]);
+        assert_eq!(pointers("/contact/email"), vec!["/users/0/contact/email"]);
+        assert_eq!(pointers("mail"), Vec::<&str>::new());
+        assert_eq!(pointers(""), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn dispatch() {
+        let res = JSONParser::parse(r#"[{"id": 1, "tags": ["a"]}, 2]"#, ParseOptions::default()).unwrap();
+        let RootKind::Array(elements) = res.dispatch() else { panic!("expected an array") };
+        assert_eq!(elements.len(), 2);
+        assert_eq!(elements[0].index(), 0);
+        let pointers: Vec<&str> = elements[0].entries().iter().map(|entry| entry.pointer.pointer.as_str()).collect();
+        assert_eq!(pointers, vec!["/0", "/0/id", "/0/tags", "/0/tags/0"]);
+        assert_eq!(elements[1].find_node_at("/1").unwrap().value, Some("2"));
+
+        let res = JSONParser::parse("[]", ParseOptions::default()).unwrap();
+        assert!(matches!(res.dispatch(), RootKind::Array(elements) if elements.is_empty()));
+
+        let res = JSONParser::parse(r#"[{"id": 1}, 2]"#, ParseOptions::default().array_index_base(1)).unwrap();
+        let RootKind::Array(elements) = res.dispatch() else { panic!("expected an array") };
+        assert_eq!(elements.iter().map(|element| (element.index(), element.entries().len())).collect::<Vec<_>>(), vec![(1, 2), (2, 1)]);
+        let mut res = JSONParser::parse("[1, 2]", ParseOptions::default()).unwrap();
+        res.json[2].pointer.pointer = "/4294967295".to_string();
+        let RootKind::Array(elements) = res.dispatch() else { panic!("expected an array") };
+        assert_eq!(elements.iter().map(|element| element.index()).collect::<Vec<_>>(), vec![0, 4294967295]);
+
+        let res = JSONParser::parse(r#"{"id": 1, "name": "Bash"}"#, ParseOptions::default()).unwrap();
+        let RootKind::Object(entries) = res.dispatch() else { panic!("expected an object") };
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].pointer.pointer, "/name");
+
+        let mut res = JSONParser::parse("{}", ParseOptions::default()).unwrap();
+        res.json.push(FlatJsonValue { pointer: PointerKey::from_pointer("".to_string(), ValueType::Bool, 0, 0), value: Some("true") });
+        let RootKind::Scalar(entry) = res.dispatch() else { panic!("expected a scalar") };
+        assert_eq!(entry.value, Some("true"));
+    }
+
+    #[test]
+    fn rows() {
+        let res = JSONParser::parse(r#"[{"id": 1, "tags": ["a"]}, 2, []]"#, ParseOptions::default()).unwrap();
+        let rows: Vec<RowView<&str>> = res.rows("").collect();
+        assert_eq!(rows.len(), 3);
+        let pointers: Vec<&str> = rows[0].entries().iter().map(|entry| entry.pointer.pointer.as_str()).collect();
+        assert_eq!(pointers, vec!["/0", "/0/id", "/0/tags", "/0/tags/0"]);
+        assert_eq!(rows[1].find_node_at("/1").unwrap().value, Some("2"));
+        assert_eq!(rows[2].index(), 2);
+
+        let res = JSONParser::parse(r#"{"items": [{"id": 1}, {"id": 2}], "count": 2}"#, ParseOptions::default()).unwrap();
+        let ids: Vec<(usize, Option<&str>)> = res.rows("/items").map(|row| (row.index, row.find_node_at(&format!("/items/{}/id", row.index)).unwrap().value)).collect();
+        assert_eq!(ids, vec![(0, Some("1")), (1, Some("2"))]);
+        assert_eq!(res.rows("/count").count(), 0);
+        assert_eq!(res.rows("/missing").count(), 0);
+    }
+
+    #[test]
+    fn parse_json_seq() {
+        for input in ["\x1e{\"id\": 1}\n\x1e[true]\n", "\x1e{\"id\": 1}\x1e[true]", "\n\x1e{\"id\": 1}\n\x1e\x1e \n\x1e[true]\n"] {
+            let records: Vec<_> = JSONParser::parse_json_seq(input, ParseOptions::default()).collect();
+            assert_eq!(records.len(), 2, "{:?}", input);
+            let first = records[0].as_ref().unwrap();
+            assert_eq!((first.json[0].pointer.pointer.as_str(), first.json[0].value), ("/id", Some("1")));
+            assert_eq!(records[1].as_ref().unwrap().json[1].value, Some("true"));
+        }
+
+        let input = "\x1e{\"id\": 1}\n\x1e{\"id\" 2}\n\x1e[3, \n\x1e{\"id\": 4}\n";
+        let records: Vec<_> = JSONParser::parse_json_seq(input, ParseOptions::default()).collect();
+        assert_eq!(records.len(), 4);
+        assert!(records[0].is_ok() && records[3].is_ok());
+        assert_eq!(records[1].as_ref().unwrap_err().offset(), 6);
+        assert!(records[2].as_ref().unwrap_err().is_unexpected_eof());
+        assert_eq!(records[3].as_ref().unwrap().json[0].value, Some("4"));
+    }
+
+    #[test]
+    fn append_array_elements() {
+        let mut res = JSONParser::parse(r#"[{"a": 1}]"#, ParseOptions::default()).unwrap();
+        JSONParser::append_array_elements(&mut res, &[r#"{"a": 2, "b": [true]}"#, "3"], "", ParseOptions::default()).unwrap();
+        let entries: Vec<(&str, u8, usize, usize)> = res.json.iter().map(|e| (e.pointer.pointer.as_str(), e.pointer.depth, e.pointer.index, e.pointer.position)).collect();
+        assert_eq!(entries, vec![("", 1, 0, 0), ("/0", 1, 0, 1), ("/0/a", 2, 0, 2), ("/1", 1, 1, 3), ("/1/a", 2, 1, 4), ("/1/b", 2, 1, 5), ("/1/b/0", 3, 1, 6), ("/2", 1, 2, 7)]);
+        assert_eq!(res.json[0].pointer.value_type, ValueType::Array(3));
+        assert_eq!(res.max_json_depth, 2);
+        JSONParser::append_array_elements(&mut res, &["4"], "", ParseOptions::default()).unwrap();
+        assert_eq!((res.json[8].pointer.pointer.as_str(), res.json[8].pointer.position), ("/3", 8));
+        assert!(JSONParser::append_array_elements(&mut res, &["5", "{"], "", ParseOptions::default()).is_err());
+        assert_eq!(res.json.len(), 9);
+        assert_eq!(res.json[0].pointer.value_type, ValueType::Array(4));
+
+        let mut res = JSONParser::parse(r#"{"logs": []}"#, ParseOptions::default()).unwrap();
+        JSONParser::append_array_elements(&mut res, &[r#""started""#], "/logs", ParseOptions::default()).unwrap();
+        assert_eq!(res.json[1].pointer.pointer, "/logs/0");
+        assert_eq!(res.json[1].value, Some("started"));
+        assert_eq!(res.json[0].pointer.value_type, ValueType::Array(1));
+    }
+
+    #[test]
+    fn structurally_equal() {
+        let a = JSONParser::parse(r#"{"a": 1, "b": [true, "x"], "c": null}"#, ParseOptions::default()).unwrap();
+        let b = JSONParser::parse(r#"{"c": null, "b": [true, "x"], "a": 1.0}"#, ParseOptions::default()).unwrap();
+        assert!(a.structurally_equal(&a, false));
+        assert!(!a.structurally_equal(&b, false));
+        assert!(a.structurally_equal(&b, true));
+
+        let mut reversed = a.clone();
+        reversed.json.reverse();
+        assert!(a.structurally_equal(&reversed, false));
+        let c = JSONParser::parse(r#"{"a": "1", "b": [true, "x"], "c": null}"#, ParseOptions::default()).unwrap();
+        assert!(!a.structurally_equal(&c, true));
+        let d = JSONParser::parse(r#"{"a": 1, "b": [true, "x"], "c": null, "d": 2}"#, ParseOptions::default()).unwrap();
+        assert!(!a.structurally_equal(&d, false));
+        let zero = JSONParser::parse(r#"{"a": -0}"#, ParseOptions::default()).unwrap();
+        assert!(zero.structurally_equal(&JSONParser::parse(r#"{"a": 0.0}"#, ParseOptions::default()).unwrap(), true));
+    }
+
+    #[test]
+    fn prune_to_depth() {
+        let json = r#"{"a": {"b": {"c": 1}}, "d": [1, [2]], "e": true}"#;
+        let res = JSONParser::parse(json, ParseOptions::default()).unwrap();
+        let mut pruned = res.prune_to_depth(1);
+        let entries: Vec<(&str, ValueType)> = pruned.json.iter().map(|e| (e.pointer.pointer.as_str(), e.pointer.value_type)).collect();
+        assert_eq!(entries, vec![("/a", ValueType::Object(false)), ("/d", ValueType::Array(2)), ("/e", ValueType::Bool)]);
+        assert_eq!(pruned.max_json_depth, 1);
+        assert_eq!(res.json.len(), 8);
+
+        JSONParser::change_depth(&mut pruned, ParseOptions::default()).unwrap();
+        let pointers: Vec<&str> = pruned.json.iter().map(|e| e.pointer.pointer.as_str()).collect();
+        assert_eq!(pointers, vec!["/a", "/d", "/e", "/a/b", "/a/b/c"]);
+        assert_eq!(res.prune_to_depth(3).json.len(), 8);
+    }
+
+    #[test]
+    fn validate_options() {
+        assert_eq!(ParseOptions::default().validate(), Ok(()));
+        assert_eq!(ParseOptions::default().prefix("/root".to_string()).start_parse_at("/skills".to_string()).array_index_base(1).fold_single_key_objects(true).validate(), Ok(()));
+        assert!(ParseOptions::default().prefix("root".to_string()).validate().unwrap_err().contains("prefix"));
+        assert!(ParseOptions::default().start_parse_at("skills".to_string()).validate().unwrap_err().contains("start_parse_at"));
+        assert!(ParseOptions::default().array_index_base(2).validate().unwrap_err().contains("array_index_base"));
+        assert!(ParseOptions::default().fold_single_key_objects(true).emit_containers(false).validate().unwrap_err().contains("emit_containers"));
+        assert!(ParseOptions::default().fold_single_key_objects(true).fold_separator("").validate().unwrap_err().contains("fold_separator"));
+        assert_eq!(ParseOptions::default().fold_separator("").validate(), Ok(()));
+        assert!(JSONParser::parse_owned(r#"{"a": "b"}"#, ParseOptions::default().intern_values(true)).unwrap_err().is_invalid_options());
+
+        let err = JSONParser::parse(r#"{"a": 1}"#, ParseOptions::default().array_index_base(2)).unwrap_err();
+        assert_eq!(err, ParseError::InvalidOptions { reason: "array_index_base should be 0 or 1, not 2".to_string(), offset: 0 });
+        assert!(err.is_invalid_options());
+        assert_eq!(err.offset(), 0);
+        assert!(JSONParser::parse_projection(r#"{"a": 1}"#, &["/a"], ParseOptions::default().prefix("root".to_string())).unwrap_err().is_invalid_options());
+    }
+
+    #[test]
+    fn fold_single_key_objects() {
+        let res = JSONParser::parse(r#"{"a": {"b": {"c": 1}}}"#, ParseOptions::default().fold_single_key_objects(true)).unwrap();
+        let entries: Vec<(&str, ValueType, u8, Option<&str>)> = res.json.iter().map(|e| (e.pointer.pointer.as_str(), e.pointer.value_type, e.pointer.depth, e.value)).collect();
+        assert_eq!(entries, vec![("/a.b.c", ValueType::Number, 1, Some("1"))]);
+
+        let json = r#"{"root": {"item": [{"name": {"text": "x"}}, {"id": 1, "name": {"text": "y"}}]}, "empty": {}, "flat": {"a": 1, "b": 2}}"#;
+        let res = JSONParser::parse(json, ParseOptions::default().fold_single_key_objects(true)).unwrap();
+        let entries: Vec<(&str, u8)> = res.json.iter().map(|e| (e.pointer.pointer.as_str(), e.pointer.depth)).collect();
+        assert_eq!(entries, vec![
+            ("/root.item", 1),
+            ("/root.item/0", 2),
+            ("/root.item/0/name.text", 3),
+            ("/root.item/1", 2),
+            ("/root.item/1/id", 3),
+            ("/root.item/1/name.text", 3),
+            ("/empty", 1),
+            ("/flat", 1),
+            ("/flat/a", 2),
+            ("/flat/b", 2),
+        ]);
+        assert_eq!(res.validate_pointers(), Ok(()));
+
+        let res = JSONParser::parse(json, ParseOptions::default().fold_single_key_objects(true).fold_separator("/")).unwrap();
+        assert_eq!(res.json[0].pointer.pointer, "/root/item");
+        assert!(res.json.iter().any(|e| e.pointer.pointer == "/root/item/0/name/text" && e.value == Some("x")));
+        assert!(!res.json.iter().any(|e| e.pointer.pointer == "/root/item/0/name"));
+        assert_eq!(res.validate_pointers(), Ok(()));
+    }
+
+    #[test]
+    fn breadth_stats() {
+        let json = r#"{"a": [1, 2, 3], "b": {"c": [], "d": {"e": 1, "f": 2, "g": 3, "h": [[1, 2]]}}, "i": [{}]}"#;
+        let stats = JSONParser::parse(json, ParseOptions::default()).unwrap().breadth_stats();
+        assert_eq!(stats, BreadthStats { max_array_len: 3, max_object_keys: 4, total_arrays: 5, total_objects: 4, total_array_elements: 7, total_object_keys: 9 });
+        assert_eq!(stats.average_array_len(), 1.4);
+        assert_eq!(stats.average_object_keys(), 2.25);
+        assert_eq!(JSONParser::parse(json, ParseOptions::default()).unwrap().max_array_len(), 3);
+
+        // containers beyond max_depth are not measured
+        let stats = JSONParser::parse(json, ParseOptions::default().max_depth(2)).unwrap().breadth_stats();
+        assert_eq!(stats, BreadthStats { max_array_len: 3, max_object_keys: 3, total_arrays: 3, total_objects: 2, total_array_elements: 4, total_object_keys: 5 });
+
+        let stats = JSONParser::parse("[[1, 2], [3]]", ParseOptions::default().prefix("/root".to_string())).unwrap().breadth_stats();
+        assert_eq!((stats.max_array_len, stats.total_arrays, stats.total_objects), (2, 3, 0));
+        assert_eq!(JSONParser::parse("{}", ParseOptions::default()).unwrap().breadth_stats(), BreadthStats::default());
+    }
+
+    #[test]
+    fn common_prefix() {
+        let res = JSONParser::parse(r#"{"response": {"data": [{"id": 1}, {"id": 2}]}}"#, ParseOptions::default().emit_containers(false)).unwrap();
+        assert_eq!(res.common_prefix(), "/response/data");
+        let res = JSONParser::parse(r#"{"a": {"bc": 1, "bd": 2}}"#, ParseOptions::default()).unwrap();
+        assert_eq!(res.common_prefix(), "/a");
+        let res = JSONParser::parse(r#"{"a": {"bc": 1, "bcd": 2}}"#, ParseOptions::default().emit_containers(false)).unwrap();
+        assert_eq!(res.common_prefix(), "/a");
+        let res = JSONParser::parse(r#"{"a": {"b": 1}}"#, ParseOptions::default().emit_containers(false)).unwrap();
+        assert_eq!(res.common_prefix(), "/a/b");
+        let res = JSONParser::parse(r#"{"a": 1, "b": 2}"#, ParseOptions::default()).unwrap();
+        assert_eq!(res.common_prefix(), "/");
+        let res = JSONParser::parse("[1]", ParseOptions::default()).unwrap();
+        assert_eq!(res.common_prefix(), "/");
+        let res = JSONParser::parse("{}", ParseOptions::default()).unwrap();
+        assert_eq!(res.common_prefix(), "/");
+    }
+
+    #[test]
+    fn parse_as_element() {
+        let json = r#"{"data": [1, 2, {"a": 1, "b": [true]}]}"#;
+        let full = JSONParser::parse(json, ParseOptions::default()).unwrap();
+        let expected: Vec<(&str, ValueType, u8)> = full.json.iter().filter(|e| e.pointer.pointer.starts_with("/data/2"))
+            .map(|e| (e.pointer.pointer.as_str(), e.pointer.value_type, e.pointer.depth)).collect();
+        let res = JSONParser::parse_as_element(r#"{"a": 1, "b": [true]}"#, "/data", 2, ParseOptions::default()).unwrap();
+        assert_eq!(res.json.iter().map(|e| (e.pointer.pointer.as_str(), e.pointer.value_type, e.pointer.depth)).collect::<Vec<_>>(), expected);
+        assert!(res.json.iter().all(|e| e.pointer.index == 2));
+        assert_eq!(res.json[0].value, Some(r#"{"a": 1, "b": [true]}"#));
+
+        let res = JSONParser::parse_as_element(r#""text""#, "", 7, ParseOptions::default()).unwrap();
+        assert_eq!(res.json.len(), 1);
+        assert_eq!(res.json[0].pointer.pointer, "/7");
+        assert_eq!(res.json[0].pointer.depth, 1);
+        assert_eq!(res.json[0].value, Some("text"));
+        let res = JSONParser::parse_as_element(r#""text""#, "", 7, ParseOptions::default().array_index_base(1)).unwrap();
+        assert_eq!((res.json[0].pointer.pointer.as_str(), res.json[0].pointer.index), ("/8", 8));
+
+        assert!(JSONParser::parse_as_element("", "/data", 0, ParseOptions::default()).unwrap_err().is_unexpected_eof());
+    }
+
+    #[test]
+    fn iter_leaves_and_containers() {
+        let json = r#"{"a": 1, "b": null, "c": [true, {"d": "e"}], "f": {}}"#;
+        let res = JSONParser::parse(json, ParseOptions::default()).unwrap();
+        let leaves: Vec<&str> = res.iter_leaves().map(|e| e.pointer.pointer.as_str()).collect();
+        assert_eq!(leaves, vec!["/a", "/b", "/c/0", "/c/1/d"]);
+        let containers: Vec<&str> = res.iter_containers().map(|e| e.pointer.pointer.as_str()).collect();
+        assert_eq!(containers, vec!["/c", "/c/1", "/f"]);
+        assert_eq!(leaves.len() + containers.len(), res.json.len());
+    }
+
+    #[test]
+    fn merge_arrays() {
+        let pages = [r#"[{"id": 1}, {"id": 2, "tags": ["a"]}]"#, "[]", r#"[{"id": 3, "name": "c"}]"#];
+        let results: Vec<ParseResultOwned> = pages.iter().map(|page| JSONParser::parse(page, ParseOptions::default()).unwrap().to_owned()).collect();
+        let merged = ParseResultOwned::merge_arrays(results);
+        let entries: Vec<(&str, ValueType, usize)> = merged.json.iter().map(|e| (e.pointer.pointer.as_str(), e.pointer.value_type, e.pointer.index)).collect();
+        assert_eq!(entries, vec![
+            ("", ValueType::Array(3), 0),
+            ("/0", ValueType::Object(true), 0),
+            ("/0/id", ValueType::Number, 0),
+            ("/1", ValueType::Object(true), 1),
+            ("/1/id", ValueType::Number, 1),
+            ("/1/tags", ValueType::Array(1), 1),
+            ("/1/tags/0", ValueType::String, 1),
+            ("/2", ValueType::Object(true), 2),
+            ("/2/id", ValueType::Number, 2),
+            ("/2/name", ValueType::String, 2),
+        ]);
+        assert!(merged.json.windows(2).all(|w| w[0].pointer.position < w[1].pointer.position));
+        assert_eq!(merged.validate_pointers(), Ok(()));
+        #[cfg(feature = "indexmap")] {
+            let mut json = merged.json.clone();
+            assert_eq!(serialize_to_json(&mut json).to_json().replace([' ', '\n'], ""), r#"[{"id":1},{"id":2,"tags":["a"]},{"id":3,"name":"c"}]"#);
+        }
+
+        let merged = ParseResultOwned::merge_arrays(vec![]);
+        assert_eq!(merged.json.len(), 1);
+        assert_eq!(merged.json[0].pointer.value_type, ValueType::Array(1));
+        let leaves = ["[1, 2]", "[3]"].iter().map(|page| JSONParser::parse(page, ParseOptions::default().emit_containers(false)).unwrap().to_owned()).collect();
+        let merged = ParseResultOwned::merge_arrays(leaves);
+        assert_eq!(merged.leaf_pairs(), vec![("/0".to_string(), Some("1".to_string())), ("/1".to_string(), Some("2".to_string())), ("/2".to_string(), Some("3".to_string()))]);
+    }
+
+    #[test]
+    fn get_or() {
+        let json = r#"{"timeout": 10, "retries": "3", "ratio": 0.5, "verbose": true, "name": "svc", "proxy": null, "limits": {"cpu": 2}, "port": "http"}"#;
+        let res = JSONParser::parse(json, ParseOptions::default()).unwrap();
+        assert_eq!(res.get_or("/timeout", 30), 10);
+        assert_eq!(res.get_or("/retries", 5_u8), 3);
+        assert_eq!(res.get_or("/ratio", 1.0), 0.5);
+        assert!(res.get_or("/verbose", false));
+        assert_eq!(res.get_or("/name", String::new()), "svc");
+        assert_eq!(res.get_or("/limits/cpu", 1), 2);
+        assert_eq!(res.get_or("/missing", 30), 30);
+        assert_eq!(res.get_or("/proxy", "none".to_string()), "none");
+        assert_eq!(res.get_or("/limits", 7), 7);
+        assert_eq!(res.get_or("/port", 8080), 8080);
+        assert_eq!(res.get_or("/ratio", 1), 1);
+
+    }
+
+    #[test]
+    fn try_get() {
+        let json = r#"{"timeout": 10, "port": "http", "proxy": null, "limits": {"cpu": 2}}"#;
+        let res = JSONParser::parse(json, ParseOptions::default()).unwrap();
+        assert_eq!(res.try_get::<u16>("/timeout"), Ok(Some(10)));
+        assert_eq!(res.try_get::<u16>("/limits/cpu"), Ok(Some(2)));
+        assert_eq!(res.try_get::<u16>("/missing"), Ok(None));
+        assert_eq!(res.try_get::<u16>("/proxy"), Ok(None));
+        let err = res.try_get::<u16>("/port").unwrap_err();
+        assert_eq!(err, TypeMismatch { pointer: "/port".to_string(), expected: "u16" });
+        assert_eq!(err.to_string(), "Expected u16 at /port");
+        assert_eq!(res.try_get::<u16>("/limits").unwrap_err().pointer, "/limits");
+    }
+
+    #[test]
+    fn normalize_array_indices() {
+        let json = r#"{"items": [{"id": 1}, {"id": 2, "tags": ["a"]}, {"id": 3}], "count": 3}"#;
+        let mut res = JSONParser::parse(json, ParseOptions::default().keep_object_raw_data(false)).unwrap().to_owned();
+        let inserted = res.json.iter().filter(|entry| entry.pointer.pointer.starts_with("/items/0")).map(|entry| {
+            let mut entry = entry.clone();
+            entry.pointer.pointer = entry.pointer.pointer.replacen("/items/0", "/items/1", 1);
+            entry.value = entry.value.map(|value| if value == "1" { "4".to_string() } else { value });
+            entry
+        }).collect::<Vec<_>>();
+        res.json.extend(inserted);
+        let err = res.validate_pointers().unwrap_err();
+        assert!(err.contains("/items/1"));
+        res.json.retain(|entry| !entry.pointer.pointer.starts_with("/items/0"));
+
+        res.normalize_array_indices("/items");
+        assert_eq!(res.validate_pointers(), Ok(()));
+        let pointers = |res: &ParseResultOwned| res.json.iter().filter(|entry| entry.pointer.pointer.starts_with("/items/")).map(|entry| (entry.pointer.pointer.clone(), entry.value.clone())).collect::<Vec<_>>();
+        assert_eq!(pointers(&res), vec![
+            ("/items/0".to_string(), None), ("/items/0/id".to_string(), Some("2".to_string())), ("/items/0/tags".to_string(), None), ("/items/0/tags/0".to_string(), Some("a".to_string())),
+            ("/items/2".to_string(), None), ("/items/2/id".to_string(), Some("3".to_string())),
+            ("/items/1".to_string(), None), ("/items/1/id".to_string(), Some("4".to_string())),
+        ]);
+        assert_eq!(res.json.iter().find(|entry| entry.pointer.pointer == "/items").unwrap().pointer.value_type, ValueType::Array(3));
+        #[cfg(feature = "indexmap")]
+        assert_eq!(serialize_to_json(&mut res.json).to_json().replace([' ', '\n'], ""), r#"{"items":[{"id":2,"tags":["a"]},{"id":4},{"id":3}],"count":3}"#);
+
+        let mut res = JSONParser::parse("[1, 2, 3]", ParseOptions::default()).unwrap();
+        res.json.remove(1);
+        res.normalize_array_indices("");
+        assert_eq!(res.json.iter().map(|entry| (entry.pointer.pointer.as_str(), entry.pointer.index)).collect::<Vec<_>>(), vec![("", 0), ("/0", 0), ("/1", 1)]);
+    }
+
+    #[test]
+    fn with_interned_pointers() {
+        let json = r#"{"a": 1, "b": {"c": "x"}, "a": 2, "d": [true, null]}"#;
+        let interned = JSONParser::parse(json, ParseOptions::default().keep_object_raw_data(false)).unwrap().with_interned_pointers();
+        assert_eq!(interned.pointer_count(), 6);
+        assert_eq!(interned.entries.len(), 7);
+        let a = interned.id_of("/a").unwrap();
+        assert_eq!(interned.resolve(a), "/a");
+        assert_eq!(interned.entries.iter().filter(|entry| entry.id == a).map(|entry| entry.value).collect::<Vec<_>>(), vec![Some("1"), Some("2")]);
+        let c = interned.id_of("/b/c").unwrap();
+        assert_eq!(interned.find(c), Some(&InternedEntry { id: c, value_type: ValueType::String, value: Some("x") }));
+        assert_eq!(interned.resolve(interned.entries[6].id), "/d/1");
+        assert_eq!(interned.id_of("/missing"), None);
+        assert_eq!((0..interned.pointer_count() as u32).map(|id| interned.resolve(id)).collect::<Vec<_>>(), vec!["/a", "/b", "/b/c", "/d", "/d/0", "/d/1"]);
+    }
+
+    #[test]
+    fn first_and_last_entries() {
+        let json = r#"{"b": {"c": [1, 2]}, "a": 10}"#;
+        let pointer = |entry: Option<&FlatJsonValue<&str>>| entry.map(|entry| entry.pointer.pointer.clone());
+        let res = JSONParser::parse(json, ParseOptions::default()).unwrap();
+        assert_eq!(pointer(res.first_entry()), Some("/b".to_string()));
+        assert_eq!(pointer(res.last_entry()), Some("/a".to_string()));
+        assert_eq!(pointer(res.min_pointer()), Some("/a".to_string()));
+        assert_eq!(pointer(res.max_pointer()), Some("/b/c/1".to_string()));
+
+        let res = JSONParser::parse(json, ParseOptions::default().envelope_only(true)).unwrap();
+        assert_eq!(pointer(res.first_entry()), Some("".to_string()));
+        let res = JSONParser::parse(r#"[{"a": 1}, 2]"#, ParseOptions::default().prefix("/root".to_string())).unwrap();
+        assert_eq!(pointer(res.first_entry()), Some("".to_string()));
+        assert_eq!(pointer(res.last_entry()), Some("/root/1".to_string()));
+        let res = JSONParser::parse(r#"{"a": 10, "b": {"c": [1, 2]}}"#, ParseOptions::default().start_parse_at("/b".to_string())).unwrap();
+        assert_eq!(pointer(res.first_entry()), Some("/b".to_string()));
+
+        let mut res = JSONParser::parse(json, ParseOptions::default().max_depth(1)).unwrap();
+        JSONParser::change_depth(&mut res, ParseOptions::default().max_depth(3)).unwrap();
+        assert_eq!(pointer(res.json.last()), Some("/b/c/1".to_string()));
+        assert_eq!(pointer(res.last_entry()), Some("/a".to_string()));
+
+        let res = JSONParser::parse("{}", ParseOptions::default()).unwrap();
+        assert_eq!(pointer(res.first_entry()), None);
+        assert_eq!(pointer(res.max_pointer()), None);
+    }
+
+    #[test]
+    fn retain_pointers() {
+        let json = r#"{"users": [{"id": 1, "name": "a", "email": "a@b.io", "age": 20, "tags": ["x"]}, {"id": 2, "name": "b", "email": "b@b.io", "age": 30, "tags": []}], "total": 2}"#;
+        let mut res = JSONParser::parse(json, ParseOptions::default().keep_object_raw_data(false)).unwrap();
+        res.retain_pointers(&["/users/*/id", "users/*/email"], true);
+        assert_eq!(res.json.iter().map(|entry| entry.pointer.pointer.as_str()).collect::<Vec<_>>(),
+                   vec!["/users", "/users/0", "/users/0/id", "/users/0/email", "/users/1", "/users/1/id", "/users/1/email"]);
+        #[cfg(feature = "indexmap")]
+        assert_eq!(serialize_to_json(&mut res.json).to_json().replace([' ', '\n'], ""), r#"{"users":[{"id":1,"email":"a@b.io"},{"id":2,"email":"b@b.io"}]}"#);
+
+        let mut res = JSONParser::parse(json, ParseOptions::default()).unwrap();
+        res.retain_pointers(&["/users/1/tags", "/total", "/users/x/id"], false);
+        assert_eq!(res.json.iter().map(|entry| entry.pointer.pointer.as_str()).collect::<Vec<_>>(), vec!["/users/1/tags", "/total"]);
+
+        let mut res = JSONParser::parse(r#"[{"a": 1, "b": 2}]"#, ParseOptions::default().prefix("/root".to_string())).unwrap();
+        res.retain_pointers(&["/root/*/b"], true);
+        assert_eq!(res.json.iter().map(|entry| entry.pointer.pointer.as_str()).collect::<Vec<_>>(), vec!["", "/root/0", "/root/0/b"]);
+    }
+
+    #[test]
+    fn leaf_pairs() {
+        let json = r#"{"a": 1, "b": null, "c": [true, {"d": "e"}], "f": {}, "g": {"h": [2]}}"#;
+        let expected = vec![("/a".to_string(), Some("1".to_string())), ("/b".to_string(), None), ("/c/0".to_string(), Some("true".to_string()))];
+        // children of /c/1 and /g/h are beyond max_depth
+        let mut res = JSONParser::parse(json, ParseOptions::default().max_depth(2)).unwrap();
+        assert_eq!(res.leaf_pairs(), expected);
+        assert_eq!(JSONParser::parse(json, ParseOptions::default().max_depth(2).emit_containers(false)).unwrap().leaf_pairs(), expected);
+        res.json.push(FlatJsonValue { pointer: PointerKey::from_pointer("/i".to_string(), ValueType::None, 1, 20), value: None });
+        assert_eq!(res.leaf_pairs().len(), 3);
+    }
+
+    #[test]
+    fn source_span() {
+        let json = "\u{feff} \n {\"a\": [1, 2]} \n";
+        let res = JSONParser::parse(json, ParseOptions::default()).unwrap();
+        assert_eq!(&json[res.source_span.clone()], r#"{"a": [1, 2]}"#);
+        let res = JSONParser::parse(json, ParseOptions::default().max_depth(1)).unwrap().to_owned();
+        assert_eq!(&json[res.source_span], r#"{"a": [1, 2]}"#);
+        let json = " [1, {\"b\": null}]";
+        assert_eq!(JSONParser::parse(json, ParseOptions::default()).unwrap().source_span, 1..json.len());
+        assert_eq!(JSONParser::parse_as_element(" 12 ", "", 0, ParseOptions::default()).unwrap().source_span, 1..3);
+        assert_eq!(JSONParser::parse_projection(json, &["/0"], ParseOptions::default()).unwrap().source_span, 1..json.len());
+    }
+
+    #[test]
+    fn build_from_iterator() {
+        let mut res: ParseResult<String> = vec![
+            (PointerKey::from_pointer("/name".to_string(), ValueType::String, 1, 1), Some("Bash".to_string())),
+            (PointerKey::from_pointer("/tags".to_string(), ValueType::Array(2), 1, 2), None),
+        ].into_iter().collect();
+        res.extend(vec![
+            FlatJsonValue { pointer: PointerKey::from_pointer("/tags/0".to_string(), ValueType::Number, 2, 3), value: Some("1".to_string()) },
+            FlatJsonValue { pointer: PointerKey::from_pointer("/tags/1".to_string(), ValueType::Null, 2, 4), value: None },
+        ]);
+        assert_eq!(res.json.len(), 4);
+        assert_eq!(res.max_json_depth, 2);
+        assert_eq!(res.validate_pointers(), Ok(()));
+
+        let mut invalid = res.clone();
+        invalid.extend(vec![(PointerKey::from_pointer("/tags/x".to_string(), ValueType::Bool, 2, 5), Some("true".to_string()))]);
+        assert!(invalid.validate_pointers().unwrap_err().contains("not an index"));
+        let mut invalid = res.clone();
+        invalid.extend(vec![(PointerKey::from_pointer("/name/x".to_string(), ValueType::Bool, 2, 5), Some("true".to_string()))]);
+        assert!(invalid.validate_pointers().is_err());
+        let mut invalid = res.clone();
+        invalid.extend(vec![(PointerKey::from_pointer("/name".to_string(), ValueType::Bool, 1, 5), Some("true".to_string()))]);
+        assert!(invalid.validate_pointers().unwrap_err().contains("duplicated"));
+        let mut invalid = res.clone();
+        invalid.json[2].pointer.depth = 3;
+        assert!(invalid.validate_pointers().unwrap_err().contains("depth"));
+        // orphan entries have no parent to compare their depth with
+        let mut invalid = res.clone();
+        invalid.extend(vec![(PointerKey::from_pointer("/a/b".to_string(), ValueType::Bool, 1, 5), Some("true".to_string()))]);
+        assert!(invalid.validate_pointers().unwrap_err().contains("segments"));
+        let invalid: ParseResult<String> = vec![(PointerKey::from_pointer("name".to_string(), ValueType::Null, 1, 1), None)].into_iter().collect();
+        assert!(invalid.validate_pointers().is_err());
+
+        let parsed = JSONParser::parse(r#"[{"a": [1]}, 2]"#, ParseOptions::default()).unwrap();
+        assert_eq!(parsed.validate_pointers(), Ok(()));
+    }
+
+    #[test]
+    fn promote_value_type() {
+        assert!(ValueType::None.rank() < ValueType::Null.rank());
+        assert!(ValueType::Number.rank() < ValueType::String.rank());
+        assert_eq!(ValueType::promote(ValueType::Number, ValueType::Null), ValueType::Number);
+        assert_eq!(ValueType::promote(ValueType::Null, ValueType::Bool), ValueType::Bool);
+        assert_eq!(ValueType::promote(ValueType::None, ValueType::Null), ValueType::Null);
+        assert_eq!(ValueType::promote(ValueType::Number, ValueType::Number), ValueType::Number);
+        assert_eq!(ValueType::promote(ValueType::Number, ValueType::String), ValueType::String);
+        assert_eq!(ValueType::promote(ValueType::Bool, ValueType::Number), ValueType::String);
+        assert_eq!(ValueType::promote(ValueType::Array(2), ValueType::Array(5)), ValueType::Array(5));
+        assert_eq!(ValueType::promote(ValueType::Object(true), ValueType::Object(false)), ValueType::Object(false));
+        assert_eq!(ValueType::promote(ValueType::Array(2), ValueType::Object(true)), ValueType::String);
+        let types = [ValueType::None, ValueType::Null, ValueType::Bool, ValueType::Number, ValueType::Array(1), ValueType::Object(true), ValueType::String];
+        for a in types {
+            for b in types {
+                let promoted = ValueType::promote(a, b);
+                assert_eq!(promoted, ValueType::promote(b, a));
+                assert!(promoted.rank() >= a.rank().max(b.rank()));
+            }
+        }
+    }
+
+    #[test]
+    fn sort_array_by_key() {
+        let json = r#"{"items": [{"id": 10, "name": "c", "tags": ["x"]}, {"name": "none"}, {"id": 2, "name": "a"}, {"id": 9, "name": "b"}], "other": 1}"#;
+        let res = JSONParser::parse(json, ParseOptions::default()).unwrap();
+        let sorted = res.sort_array_by_key("/items", "id");
+        let find = |pointer: &str| sorted.json.iter().find(|e| e.pointer.pointer == pointer).map(|e| e.value);
+        assert_eq!(find("/items/0/name"), Some(Some("a")));
+        assert_eq!(find("/items/1/name"), Some(Some("b")));
+        assert_eq!(find("/items/2/name"), Some(Some("c")));
+        assert_eq!(find("/items/2/tags/0"), Some(Some("x")));
+        assert_eq!(find("/items/3/name"), Some(Some("none")));
+        assert_eq!(find("/other"), Some(Some("1")));
+        assert_eq!(sorted.json.len(), res.json.len());
+        let mut document_order = sorted.json.clone();
+        document_order.sort_by_key(|e| e.pointer.position);
+        let pointers: Vec<&str> = document_order.iter().map(|e| e.pointer.pointer.as_str()).collect();
+        assert_eq!(pointers, vec!["/items", "/items/0", "/items/0/id", "/items/0/name", "/items/1", "/items/1/id", "/items/1/name",
+                                  "/items/2", "/items/2/id", "/items/2/name", "/items/2/tags", "/items/2/tags/0", "/items/3", "/items/3/name", "/other"]);
+
+        let res = JSONParser::parse(r#"[{"k": "b"}, {"k": "a"}]"#, ParseOptions::default()).unwrap();
+        let sorted = res.sort_array_by_key("", "/k");
+        assert_eq!(sorted.json[1].pointer.pointer, "/1");
+        assert_eq!(sorted.json[1].pointer.index, 1);
+        assert_eq!(sorted.json[2].pointer.pointer, "/1/k");
+        assert_eq!(sorted.json[4].pointer.pointer, "/0/k");
+        assert_eq!(sorted.json[4].value, Some("a"));
+    }
+
+    #[test]
+    fn compact() {
+        let json = r#"{"a": "value", "b": [1, 2, 3], "c": {"d": true}}"#;
+        let mut res = JSONParser::parse(json, ParseOptions::default()).unwrap().to_owned();
+        res.json.retain(|e| e.pointer.pointer != "/b/1");
+        res.json[0].value.as_mut().unwrap().reserve(1024);
+        let len = res.json.len();
+        let res = res.compact();
+        assert_eq!(res.json.len(), len);
+        assert_eq!(res.json.capacity(), len);
+        assert_eq!(res.json[0].value.as_ref().unwrap().capacity(), "value".len());
+        assert_eq!(res.json[0].value.as_deref(), Some("value"));
+
+        let res = JSONParser::parse(json, ParseOptions::default()).unwrap().compact();
+        assert_eq!(res.json.capacity(), res.json.len());
+    }
+
+    #[test]
+    fn try_from_str() {
+        let res: ParseResultOwned = r#"{"id": 1, "name": "Bash"}"#.try_into().unwrap();
+        assert_eq!(res.json.len(), 2);
+        assert_eq!(res.json[1].pointer.pointer, "/name");
+        assert_eq!(res.json[1].value.as_deref(), Some("Bash"));
+
+        assert!(ParseResultOwned::try_from("").is_err());
+        assert!(ParseResultOwned::try_from("true").is_err());
+    }
+
+    #[test]
+    fn map_values_lowercase() {
+        let json = r#"{"name": "NV_BASIC", "id": 1, "flags": {"a": "Fire", "b": null}, "tags": ["A", "b"]}"#;
+        let mut res = JSONParser::parse(json, ParseOptions::default()).unwrap().to_owned();
+        res.map_values(|pointer, value| {
+            if matches!(pointer.value_type, ValueType::String) {
+                (value.map(|v| v.to_lowercase()), None)
+            } else {
+                (value, None)
+            }
+        });
+        let vec = &res.json;
+        assert_eq!(vec[0].pointer.pointer, "/name");
+        assert_eq!(vec[0].value.as_deref(), Some("nv_basic"));
+        assert_eq!(vec[1].value.as_deref(), Some("1"));
+        assert_eq!(vec[2].pointer.pointer, "/flags");
+        assert_eq!(vec[2].value.as_deref(), Some(r#"{"a": "Fire", "b": null}"#));
+        assert_eq!(vec[3].value.as_deref(), Some("fire"));
+        assert_eq!(vec[4].pointer.value_type, ValueType::Null);
+        assert_eq!(vec[6].value.as_deref(), Some("a"));
+        assert_eq!(vec[7].value.as_deref(), Some("b"));
+
+        res.map_values(|pointer, value| if pointer.pointer == "/id" { (None, None) } else { (value, None) });
+        assert_eq!(res.json[1].pointer.value_type, ValueType::Null);
+        assert_eq!(res.json[1].value, None);
+
+        // masked booleans become strings
+        let mut res = JSONParser::parse(r#"{"a": true, "b": "x", "c": false}"#, ParseOptions::default()).unwrap().to_owned();
+        res.map_values(|pointer, value| {
+            if matches!(pointer.value_type, ValueType::Bool) {
+                return (Some("***".to_string()), Some(ValueType::String));
+            }
+            (value, None)
+        });
+        let types: Vec<(ValueType, Option<&str>)> = res.json.iter().map(|entry| (entry.pointer.value_type, entry.value.as_deref())).collect();
+        assert_eq!(types, vec![(ValueType::String, Some("***")), (ValueType::String, Some("x")), (ValueType::String, Some("***"))]);
+        res.map_values(|_, _| (None, Some(ValueType::None)));
+        assert!(res.json.iter().all(|entry| entry.pointer.value_type == ValueType::None && entry.value.is_none()));
+    }
+}
@@ -1,7 +1,11 @@
 
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::mem;
-use crate::{concat_string, FlatJsonValue, ParseOptions, ParseResult, PointerFragment, PointerKey, ValueType};
-use crate::lexer::{Lexer, Token};
+use crate::{DuplicateKeyPolicy, FlatJsonValue, NumberFormat, ParseOptions, ParseResult, PointerFragment, PointerKey, PointerRole, ValueType};
+use crate::error::ParseError;
+use crate::lexer::{find_invalid_escape, Lexer, Token};
+use crate::string_subtype::StringSubtype;
 
 pub struct Parser<'a, 'json> {
     lexer: &'a mut Lexer<'json>,
@@ -9,28 +13,51 @@ pub struct Parser<'a, 'json> {
     pub state_seen_start_parse_at: bool,
     pub max_depth: usize,
     pub depth_after_start_at: u8,
+    steps: u64,
+    next_progress: usize,
+    // canonical output of the number recognizer differing from the number as written, by address of the number in the input
+    pub canonical_numbers: HashMap<usize, String>,
 }
 
 
 impl<'a, 'json: 'a> Parser<'a, 'json> {
     pub fn new(lexer: &'a mut Lexer<'json>) -> Self {
-        Self { lexer, current_token: None, state_seen_start_parse_at: false, max_depth: 0, depth_after_start_at: 0 }
+        Self { lexer, current_token: None, state_seen_start_parse_at: false, max_depth: 0, depth_after_start_at: 0, steps: 0, next_progress: 0, canonical_numbers: HashMap::new() }
     }
     pub fn new_for_change_depth(lexer: &'a mut Lexer<'json>, depth_after_start_at: u8, max_depth: usize) -> Self {
-        Self { lexer, current_token: None, state_seen_start_parse_at: true, max_depth, depth_after_start_at }
+        Self { lexer, current_token: None, state_seen_start_parse_at: true, max_depth, depth_after_start_at, steps: 0, next_progress: 0, canonical_numbers: HashMap::new() }
     }
 
-    pub fn parse(&mut self, parse_option: &ParseOptions, depth: u8) -> Result<ParseResult<&'json str>, String> {
+    pub fn parse(&mut self, parse_option: &ParseOptions, depth: u8) -> Result<ParseResult<&'json str>, ParseError> {
+        let envelope_option;
+        let parse_option = if parse_option.envelope_only {
+            envelope_option = ParseOptions { max_depth: 1, keep_object_raw_data: true, emit_containers: true, parse_array: true, ..parse_option.clone() };
+            &envelope_option
+        } else {
+            parse_option
+        };
         let mut values: Vec<FlatJsonValue<&'json str>> = Vec::with_capacity(64);
         self.next_token();
+        let start = self.lexer.token_start();
         let mut position = 0_usize;
+        if parse_option.max_open_containers == Some(0) && matches!(self.current_token, Some(Token::CurlyOpen | Token::SquareOpen)) {
+            return Err(ParseError::LimitExceeded { limit: "max_open_containers", offset: start });
+        }
         if let Some(current_token) = self.current_token.as_ref() {
             if matches!(current_token, Token::CurlyOpen) {
-                let mut pointer_fragment: Vec<String> = Vec::with_capacity(16);
-                if let Some(ref p) = parse_option.prefix { pointer_fragment.push(p.clone()) }
+                let mut pointer_fragment = PointerFragment::with_capacity(128);
+                if let Some(ref p) = parse_option.prefix { pointer_fragment.push(p) }
                 let i = 0;
-                // values.push((PointerKey::from_pointer("".to_string(), ValueType::Object, depth, position), None));
-                self.process_object(&mut pointer_fragment, &mut values, depth, i, parse_option, &mut position)?;
+                if parse_option.envelope_only {
+                    values.push(FlatJsonValue { pointer: PointerKey::from_pointer("".to_string(), ValueType::Object(true), depth.saturating_sub(1), i), value: None });
+                    if parse_option.annotate_roles {
+                        values[0].pointer.role = Some(PointerRole::RootObject);
+                    }
+                }
+                let key_count = self.process_object(&mut pointer_fragment, &mut values, depth, i, parse_option, &mut position)?;
+                if parse_option.envelope_only {
+                    values[0].pointer.key_count = Some(key_count);
+                }
                 return Ok(ParseResult {
                     json: values,
                     max_json_depth: self.max_depth,
@@ -38,16 +65,23 @@ impl<'a, 'json: 'a> Parser<'a, 'json> {
                     started_parsing_at: parse_option.start_parse_at.clone(),
                     parsing_prefix: parse_option.prefix.clone(),
                     depth_after_start_at: self.depth_after_start_at,
+                    source_span: start..self.lexer.reader_index(),
                 });
             }
             if matches!(current_token, Token::SquareOpen) {
-                let mut pointer_fragment: Vec<String> = Vec::with_capacity(128);
-                if let Some(ref p) = parse_option.prefix { pointer_fragment.push(p.clone()) }
+                let mut pointer_fragment = PointerFragment::with_capacity(128);
+                if let Some(ref p) = parse_option.prefix { pointer_fragment.push(p) }
                 let i = 0;
-                let pointer_index = values.len() as isize;
-
-                values.push(FlatJsonValue { pointer: PointerKey::from_pointer("".to_string(), ValueType::Array(0), depth, i), value: None });
+                let mut pointer_index: isize = -1;
+                if parse_option.emit_containers {
+                    pointer_index = values.len() as isize;
+                    values.push(FlatJsonValue { pointer: PointerKey::from_pointer("".to_string(), ValueType::Array(0), depth, i), value: None });
+                }
                 self.process_array(&mut pointer_fragment, &mut values, depth, i + 1, parse_option, &mut position, pointer_index)?;
+                if parse_option.annotate_roles && pointer_index >= 0 {
+                    // the array entry is rebuilt once its length is known
+                    values[pointer_index as usize].pointer.role = Some(PointerRole::RootArray);
+                }
                 return Ok(ParseResult {
                     json: values,
                     max_json_depth: self.max_depth,
@@ -55,44 +89,92 @@ impl<'a, 'json: 'a> Parser<'a, 'json> {
                     started_parsing_at: parse_option.start_parse_at.clone(),
                     parsing_prefix: parse_option.prefix.clone(),
                     depth_after_start_at: self.depth_after_start_at,
+                    source_span: start..self.lexer.reader_index(),
                 });
             }
-            Err(format!("Expected json to start with {{ or [ but started with {:?}", current_token))
+            Err(self.unexpected("'{' or '['"))
         } else {
-            Err("Json is empty".to_string())
+            Err(ParseError::UnexpectedEof { offset: self.lexer.reader_index() })
         }
     }
 
-    fn process_object(&mut self, route: &mut PointerFragment, target: &mut Vec<FlatJsonValue<&'json str>>, depth: u8, count: usize, parse_option: &ParseOptions, position: &mut usize) -> Result<(), String> {
+    /// Parse a single value of any type, as if it was located at `pointer`, at `depth`.
+    pub fn parse_value_at(&mut self, parse_option: &ParseOptions, pointer: &str, depth: u8) -> Result<ParseResult<&'json str>, ParseError> {
+        let mut values: Vec<FlatJsonValue<&'json str>> = Vec::with_capacity(64);
+        self.next_token();
+        let start = self.lexer.token_start();
+        let mut position = 0_usize;
+        let mut route = PointerFragment::with_capacity(128);
+        route.push(pointer);
+        self.parse_value(&mut route, &mut values, depth, 0, parse_option, &mut position)?;
+        Ok(ParseResult {
+            json: values,
+            max_json_depth: self.max_depth.max(depth as usize),
+            parsing_max_depth: parse_option.max_depth,
+            started_parsing_at: parse_option.start_parse_at.clone(),
+            parsing_prefix: Some(pointer.to_string()),
+            depth_after_start_at: self.depth_after_start_at,
+            source_span: start..self.lexer.reader_index(),
+        })
+    }
+
+    // returns the number of keys of the object, a duplicated key counting once when merged
+    fn process_object(&mut self, route: &mut PointerFragment, target: &mut Vec<FlatJsonValue<&'json str>>, depth: u8, count: usize, parse_option: &ParseOptions, position: &mut usize) -> Result<usize, ParseError> {
         if self.max_depth < depth as usize {
             self.max_depth = depth as usize;
         }
         self.next_token();
+        // key -> range of its entries in target, only tracked when duplicates have to be merged
+        let mut occurrences: Option<HashMap<Cow<'json, str>, (usize, usize)>> = None;
+        if matches!(parse_option.duplicate_key_policy, DuplicateKeyPolicy::LastValueFirstPosition) {
+            occurrences = Some(HashMap::new());
+        }
+        let mut key_count = 0;
         while let Some(ref token) = self.current_token {
-            match token {
+            let mut key_span = None;
+            let current_key = match token {
                 Token::String(key) => {
-                    route.push(concat_string!("/", key));
+                    self.check_escapes(key, parse_option)?;
+                    if parse_option.emit_keys {
+                        let start = self.lexer.token_start() + 1;
+                        key_span = Some(start..start + key.len());
+                    }
+                    let key = parse_option.key_segment(key);
+                    route.push_key(&key);
+                    key
                 }
                 Token::CurlyClose => {
                     // empty object
                     break;
                 }
-                _ => return Err(format!("Expected object to have a key at this location: {}, previous valid parsed value: {:?}", Self::concat_route(route),
-                                        target.last().map(|e| e.pointer.pointer.as_str()).unwrap_or("")))
-            }
+                _ => return Err(self.unexpected("object key"))
+            };
             self.next_token();
             if let Some(ref _token) = self.current_token {
                 match self.current_token {
                     Some(ref token) if matches!(token, Token::Colon) => {
                         self.next_token();
                     }
-                    _ => return Err(format!("Expected ':' after object key at this location: {}, previous valid parsed value: {:?}", Self::concat_route(route),
-                                    target.last().map(|e| e.pointer.pointer.as_str()).unwrap_or("")))
+                    _ => return Err(self.unexpected("':' after object key"))
                 }
             } else {
-                return Err("Expected ':' after object key".to_string());
+                return Err(self.unexpected("':' after object key"));
             }
+            let value_start = target.len();
             self.parse_value(route, target, depth, count, parse_option, position)?;
+            key_count += 1;
+            if let Some(key_span) = key_span {
+                // the member entry is the first one emitted for its value, unless it was filtered out
+                if let Some(entry) = target.get_mut(value_start).filter(|entry| entry.pointer.pointer == route.as_str()) {
+                    entry.pointer.key_span = Some(key_span);
+                }
+            }
+            if parse_option.annotate_roles {
+                Self::annotate_role(target, value_start, route, PointerRole::ObjectMember);
+            }
+            if let Some(ref mut occurrences) = occurrences {
+                Self::merge_duplicate_key(occurrences, target, route, current_key, value_start);
+            }
             self.next_token();
 
 
@@ -104,20 +186,24 @@ impl<'a, 'json: 'a> Parser<'a, 'json> {
                     route.pop();
                     break;
                 }
-                Some(ref token) if matches!(token, Token::SquareClose) => {
-                    // route.pop();
-                    panic!("End of array should not be consumed from there, json probably wrong");
-                    // break;
-                }
+                // tolerate a missing closing '}' at the end of input
                 None => break,
-                _ => return Err(format!("Expected ',' or '}}' or ']' after object value, got: {:?}", self.current_token)),
+                _ => return Err(self.unexpected("',' or '}' after object value")),
             }
             route.pop();
         }
-        Ok(())
+        Ok(occurrences.map_or(key_count, |occurrences| occurrences.len()))
     }
 
-    fn process_array(&mut self, route: &mut PointerFragment, target: &mut Vec<FlatJsonValue<&'json str>>, depth: u8, count: usize, parse_option: &ParseOptions, position: &mut usize, pointer_index: isize) -> Result<(), String> {
+    // like key spans, the entry of a value is the first one emitted for it, unless it was filtered out
+    fn annotate_role(target: &mut [FlatJsonValue<&'json str>], value_start: usize, route: &PointerFragment, role: PointerRole) {
+        if let Some(entry) = target.get_mut(value_start).filter(|entry| entry.pointer.pointer == route.as_str()) {
+            entry.pointer.role = Some(role);
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn process_array(&mut self, route: &mut PointerFragment, target: &mut Vec<FlatJsonValue<&'json str>>, depth: u8, count: usize, parse_option: &ParseOptions, position: &mut usize, pointer_index: isize) -> Result<(), ParseError> {
         let array_start_index = self.lexer.reader_index() - 1;
         self.next_token();
         let mut i = 1;
@@ -139,8 +225,12 @@ impl<'a, 'json: 'a> Parser<'a, 'json> {
                     self.depth_after_start_at = depth - 1;
                 }
                 if depth - self.depth_after_start_at <= parse_option.max_depth {
-                    route.push("/0".to_string());
+                    route.push_index(parse_option.array_index_base);
+                    let value_start = target.len();
                     self.parse_value(route, target, depth, count, parse_option, position)?;
+                    if parse_option.annotate_roles {
+                        Self::annotate_role(target, value_start, route, PointerRole::ArrayElement);
+                    }
                     route.pop();
                     self.next_token();
                     while let Some(ref token) = self.current_token {
@@ -149,8 +239,12 @@ impl<'a, 'json: 'a> Parser<'a, 'json> {
                         }
                         self.next_token();
                         if let Some(ref _token) = self.current_token {
-                            route.push(format!("/{}", i));
+                            route.push_index(i + parse_option.array_index_base);
+                            let value_start = target.len();
                             self.parse_value(route, target, depth, count, parse_option, position)?;
+                            if parse_option.annotate_roles {
+                                Self::annotate_role(target, value_start, route, PointerRole::ArrayElement);
+                            }
                             route.pop();
                         } else {
                             break;
@@ -158,14 +252,21 @@ impl<'a, 'json: 'a> Parser<'a, 'json> {
                         self.next_token();
                         i += 1;
                     }
-                } else if let Some(array_str) = self.lexer.consume_string_until_end_of_array(array_start_index, nested_array) {
+                    if !matches!(self.current_token, Some(Token::SquareClose)) {
+                        return Err(self.unexpected("',' or ']' after array value"));
+                    }
+                } else {
+                    let array_str = self.lexer.consume_string_until_end_of_array(array_start_index, nested_array)
+                        .ok_or(ParseError::UnexpectedEof { offset: self.lexer.reader_index() })?;
                     if pointer_index >= 0 {
                         let PointerKey { pointer, position, depth, .. } = mem::take(&mut target[pointer_index as usize].pointer);
                         target[pointer_index as usize] = FlatJsonValue { pointer: PointerKey::from_pointer(pointer, ValueType::Array(i), depth, position), value: Some(array_str) };
                     }
                     break;
                 }
-            } else if let Some(array_str) = self.lexer.consume_string_until_end_of_array(array_start_index, nested_array) {
+            } else {
+                let array_str = self.lexer.consume_string_until_end_of_array(array_start_index, nested_array)
+                    .ok_or(ParseError::UnexpectedEof { offset: self.lexer.reader_index() })?;
                 if pointer_index >= 0 {
                     let PointerKey { position, depth, .. } = target[pointer_index as usize].pointer;
                     target[pointer_index as usize] = FlatJsonValue { pointer: PointerKey::from_pointer(Self::concat_route(route), ValueType::Array(i), depth, position), value: Some(array_str) };
@@ -173,26 +274,53 @@ impl<'a, 'json: 'a> Parser<'a, 'json> {
                 break;
             }
         }
+        if self.current_token.is_none() {
+            return Err(ParseError::UnexpectedEof { offset: self.lexer.reader_index() });
+        }
         Ok(())
     }
 
-    fn parse_value(&mut self, route: &mut PointerFragment, target: &mut Vec<FlatJsonValue<&'json str>>, depth: u8, count: usize, parse_option: &ParseOptions, position: &mut usize) -> Result<(), String> {
+    fn parse_value(&mut self, route: &mut PointerFragment, target: &mut Vec<FlatJsonValue<&'json str>>, depth: u8, count: usize, parse_option: &ParseOptions, position: &mut usize) -> Result<(), ParseError> {
+        if let Some(ref progress) = parse_option.progress {
+            let consumed = self.lexer.reader_index();
+            if consumed >= self.next_progress {
+                self.next_progress = consumed + parse_option.progress_interval.max(1);
+                progress(consumed, self.lexer.input_len());
+            }
+        }
+        if let Some(max_steps) = parse_option.max_steps {
+            self.steps += 1;
+            if self.steps > max_steps {
+                return Err(ParseError::LimitExceeded { limit: "max_steps", offset: self.lexer.token_start() });
+            }
+        }
         match self.current_token {
             Some(ref token) => match token {
+                Token::CurlyOpen | Token::SquareOpen if depth == u8::MAX => Err(ParseError::DepthExceeded { offset: self.lexer.token_start() }),
+                // the root container and the ones containing this value are open
+                Token::CurlyOpen | Token::SquareOpen if parse_option.max_open_containers.is_some_and(|max| depth.saturating_sub(parse_option.start_depth) as usize + 2 > max) => {
+                    Err(ParseError::LimitExceeded { limit: "max_open_containers", offset: self.lexer.token_start() })
+                }
                 Token::CurlyOpen => {
                     if depth - self.depth_after_start_at <= parse_option.max_depth {
                         let start = self.lexer.reader_index();
                         if let Some(object_str) = self.lexer.consume_string_until_end_of_object(true) {
                             *position += 1;
-                            if parse_option.keep_object_raw_data || depth - self.depth_after_start_at == parse_option.max_depth {
-                                target.push(FlatJsonValue { pointer: PointerKey::from_pointer(Self::concat_route(route), ValueType::Object(depth - self.depth_after_start_at < parse_option.max_depth), depth, *position), value: Some(object_str) });
-                            } else {
-                                target.push(FlatJsonValue { pointer: PointerKey::from_pointer(Self::concat_route(route), ValueType::Object(true), depth, *position), value: None });
+                            let entry_index = target.len();
+                            if parse_option.emit_containers {
+                                if parse_option.keep_object_raw_data || depth - self.depth_after_start_at == parse_option.max_depth {
+                                    target.push(FlatJsonValue { pointer: PointerKey::from_pointer(Self::concat_route(route), ValueType::Object(depth - self.depth_after_start_at < parse_option.max_depth), depth, *position), value: Some(object_str) });
+                                } else {
+                                    target.push(FlatJsonValue { pointer: PointerKey::from_pointer(Self::concat_route(route), ValueType::Object(true), depth, *position), value: None });
+                                }
                             }
                             self.lexer.set_reader_index(start);
-                            self.process_object(route, target, depth + 1, count, parse_option, position)?;
+                            let key_count = self.process_object(route, target, depth + 1, count, parse_option, position)?;
+                            if parse_option.emit_containers {
+                                target[entry_index].pointer.key_count = Some(key_count);
+                            }
                         } else {
-                            panic!("We should no go there! we have not found matching closing curly {}", String::from_utf8_lossy(&self.lexer.reader().data()[start..start + 1000]))
+                            return Err(ParseError::UnexpectedEof { offset: self.lexer.reader_index() });
                         }
                     } else {
                         // consuming remaining token
@@ -203,7 +331,7 @@ impl<'a, 'json: 'a> Parser<'a, 'json> {
                 }
                 Token::SquareOpen => {
                     let mut pointer_index: isize = -1;
-                    if depth - self.depth_after_start_at <= parse_option.max_depth {
+                    if depth - self.depth_after_start_at <= parse_option.max_depth && parse_option.emit_containers {
                         *position += 1;
                         pointer_index = target.len() as isize;
                         target.push(FlatJsonValue { pointer: PointerKey::from_pointer(Self::concat_route(route), ValueType::Array(0), depth, *position), value: None });
@@ -212,22 +340,42 @@ impl<'a, 'json: 'a> Parser<'a, 'json> {
                     Ok(())
                 }
                 Token::String(value) => {
+                    self.check_escapes(value, parse_option)?;
+                    let value = parse_option.string_trim.trim(value);
                     if depth - self.depth_after_start_at <= parse_option.max_depth {
                         let pointer = Self::concat_route(route);
-                        if let Some(ref start_parse_at) = parse_option.start_parse_at {
-                            if pointer.starts_with(start_parse_at) {
-                                *position += 1;
-                                target.push(FlatJsonValue { pointer: PointerKey::from_pointer(pointer, ValueType::String, depth, *position), value: Some(value) });
-                            }
-                        } else {
+                        if parse_option.start_parse_at.as_ref().is_none_or(|start_parse_at| pointer.starts_with(start_parse_at)) {
                             *position += 1;
-                            target.push(FlatJsonValue { pointer: PointerKey::from_pointer(pointer, ValueType::String, depth, *position), value: Some(value) });
+                            let mut pointer = PointerKey::from_pointer(pointer, ValueType::String, depth, *position);
+                            if parse_option.detect_string_subtypes {
+                                pointer.string_subtype = StringSubtype::detect(value);
+                            }
+                            let (value, original_len) = parse_option.truncate_string(value);
+                            pointer.original_len = original_len;
+                            target.push(FlatJsonValue { pointer, value: Some(value) });
                         }
                     }
 
                     Ok(())
                 }
                 Token::Number(value) => {
+                    if parse_option.max_number_length.is_some_and(|max_number_length| value.len() > max_number_length) {
+                        return Err(ParseError::NumberTooLong { offset: self.lexer.token_start() });
+                    }
+                    let valid = match parse_option.number_format {
+                        NumberFormat::Json if parse_option.strict_numbers => is_valid_number(value),
+                        NumberFormat::Json => is_valid_lenient_number(value),
+                        NumberFormat::Custom(ref recognizer) => match recognizer(value) {
+                            Some(canonical) if canonical != *value => {
+                                self.canonical_numbers.insert(value.as_ptr() as usize, canonical.into_owned());
+                                true
+                            }
+                            canonical => canonical.is_some(),
+                        },
+                    };
+                    if !valid {
+                        return Err(ParseError::InvalidNumber { offset: self.lexer.token_start() });
+                    }
                     if depth - self.depth_after_start_at <= parse_option.max_depth {
                         let pointer = Self::concat_route(route);
                         if let Some(ref start_parse_at) = parse_option.start_parse_at {
@@ -272,9 +420,9 @@ impl<'a, 'json: 'a> Parser<'a, 'json> {
                     }
                     Ok(())
                 }
-                _ => Err(format!("Unexpected token: {:?}", token))
+                _ => Err(self.unexpected("value"))
             },
-            _ => Err("Unexpected end of input".to_string())
+            _ => Err(ParseError::UnexpectedEof { offset: self.lexer.reader_index() })
         }
     }
 
@@ -284,13 +432,44 @@ impl<'a, 'json: 'a> Parser<'a, 'json> {
             // When parse_array is disable, we allow to parse array if we set a pointer from where we start parsing and this pointer is an array itself, otherwise we would not parse anything
             || (parse_option.start_parse_at.is_some() && !self.state_seen_start_parse_at && parse_option.start_parse_at.as_ref().unwrap().eq(&Self::concat_route(route)))
     }
+    // entries of an earlier occurrence of `key` are replaced by the ones just parsed, from `value_start`, the value entry keeping its position
+    fn merge_duplicate_key(occurrences: &mut HashMap<Cow<'json, str>, (usize, usize)>, target: &mut Vec<FlatJsonValue<&'json str>>, route: &PointerFragment, key: Cow<'json, str>, value_start: usize) {
+        let value_len = target.len() - value_start;
+        let Some((start, len)) = occurrences.get(&key).copied() else {
+            occurrences.insert(key, (value_start, value_len));
+            return;
+        };
+        let first_position = target[start..start + len].iter().find(|entry| entry.pointer.pointer == route.as_str()).map(|entry| entry.pointer.position);
+        let entries = target.split_off(value_start);
+        target.splice(start..start + len, entries);
+        if let Some(first_position) = first_position {
+            if let Some(entry) = target[start..start + value_len].iter_mut().find(|entry| entry.pointer.pointer == route.as_str()) {
+                entry.pointer.position = first_position;
+            }
+        }
+        for (other_start, _) in occurrences.values_mut() {
+            if *other_start > start {
+                *other_start = *other_start + value_len - len;
+            }
+        }
+        occurrences.insert(key, (start, value_len));
+    }
+    // `string` being the content of the current token
+    fn check_escapes(&self, string: &str, parse_option: &ParseOptions) -> Result<(), ParseError> {
+        match find_invalid_escape(string) {
+            Some(i) if parse_option.deny_unknown_escape => Err(ParseError::InvalidEscape { offset: self.lexer.token_start() + 1 + i }),
+            _ => Ok(()),
+        }
+    }
+    fn unexpected(&self, expected: &'static str) -> ParseError {
+        match self.current_token {
+            Some(ref token) => ParseError::UnexpectedToken { expected, found: format!("{:?}", token), offset: self.lexer.token_start() },
+            None => ParseError::UnexpectedEof { offset: self.lexer.reader_index() },
+        }
+    }
     #[inline]
     fn concat_route(route: &PointerFragment) -> String {
-        let mut res = String::with_capacity(64);
-        for p in route {
-            res.push_str(p);
-        }
-        res
+        route.as_str().to_string()
     }
     #[inline]
     fn next_token(&mut self) {
@@ -299,10 +478,32 @@ impl<'a, 'json: 'a> Parser<'a, 'json> {
 }
 
 
+// the lexer only reads a leading '-', digits and '.'
+fn is_valid_lenient_number(number: &str) -> bool {
+    let digits = number.strip_prefix('-').unwrap_or(number);
+    let (integer, fraction) = digits.split_once('.').unwrap_or((digits, "0"));
+    !integer.is_empty() && !fraction.is_empty() && !fraction.contains('.')
+}
+
+// same grammar as the validator, RFC 8259 numbers
+fn is_valid_number(number: &str) -> bool {
+    fn digits(s: &str) -> bool {
+        !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit())
+    }
+    let number = number.strip_prefix('-').unwrap_or(number);
+    let (number, exponent) = number.split_once(['e', 'E']).unwrap_or((number, "0"));
+    let (integer, fraction) = number.split_once('.').unwrap_or((number, "0"));
+    let exponent = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+    digits(integer) && (integer == "0" || !integer.starts_with('0')) && digits(fraction) && digits(exponent)
+}
+
 #[cfg(test)]
 mod tests {
-    
-    use crate::{JSONParser, ParseOptions, ValueType};
+    use std::borrow::Cow;
+    use std::sync::{Arc, Mutex};
+    use crate::error::ParseError;
+    use crate::key_pool::KeyPool;
+    use crate::{DuplicateKeyPolicy, JSONParser, NumberFormat, ParseOptions, PointerRole, StringTrim, ValueType};
 
     #[test]
     fn object() {
@@ -314,7 +515,8 @@ mod tests {
               "aaa": true
             }"#;
 
-        let res = JSONParser::parse(json, ParseOptions::default()).unwrap();
+        // `\I` is not a JSON escape
+        let res = JSONParser::parse(json, ParseOptions::default().deny_unknown_escape(false)).unwrap();
         let vec = res.json;
         assert_eq!(vec[0].pointer.pointer, "/id");
         assert_eq!(vec[0].pointer.value_type, ValueType::Number);
@@ -899,6 +1101,26 @@ mod tests {
         assert_eq!(vec.len(), 13);
     }
 
+    #[test]
+    fn emit_containers_false() {
+        let json = r#"{"a": {"b": 1, "c": [true, {"d": null}]}, "e": "f", "g": []}"#;
+        let vec = JSONParser::parse(json, ParseOptions::default().emit_containers(false)).unwrap().json;
+        assert_eq!(vec.len(), 4);
+        assert_eq!(vec[0].pointer.pointer, "/a/b");
+        assert_eq!(vec[0].pointer.value_type, ValueType::Number);
+        assert_eq!(vec[1].pointer.pointer, "/a/c/0");
+        assert_eq!(vec[1].pointer.value_type, ValueType::Bool);
+        assert_eq!(vec[2].pointer.pointer, "/a/c/1/d");
+        assert_eq!(vec[2].pointer.value_type, ValueType::Null);
+        assert_eq!(vec[3].pointer.pointer, "/e");
+        assert_eq!(vec[3].value, Some("f"));
+
+        let vec = JSONParser::parse(r#"[{"a": 1}, 2]"#, ParseOptions::default().emit_containers(false)).unwrap().json;
+        assert_eq!(vec.len(), 2);
+        assert_eq!(vec[0].pointer.pointer, "/0/a");
+        assert_eq!(vec[1].pointer.pointer, "/1");
+    }
+
     #[test]
     fn change_depth() {
         let json = r#"
@@ -916,4 +1138,375 @@ mod tests {
         // vec.iter().for_each(|(k, v)| println!("{} {} {}", k.pointer, k.depth, v.is_some()));
         assert_eq!(vec.len(), 31);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn change_depth_computed_depth() {
+        let json = r#"{"a": {"b": [{"c": {"d": [1, {"e": null}]}}]}, "f": [[1, [2]]]}"#;
+        let mut res = JSONParser::parse(json, ParseOptions::default().max_depth(1)).unwrap();
+        for max_depth in 2..=7 {
+            JSONParser::change_depth(&mut res, ParseOptions::default().max_depth(max_depth)).unwrap();
+            assert!(res.json.iter().all(|e| e.pointer.depth == e.pointer.computed_depth()), "max_depth {}", max_depth);
+            assert_eq!(res.validate_pointers(), Ok(()));
+        }
+        assert!(res.json.iter().any(|e| e.pointer.pointer == "/a/b/0/c/d/1/e"));
+
+        let mut res = JSONParser::parse(r#"[{"a": {"b": 1}}]"#, ParseOptions::default().max_depth(1).prefix("/root".to_string())).unwrap();
+        JSONParser::change_depth(&mut res, ParseOptions::default().max_depth(3)).unwrap();
+        assert_eq!(res.validate_pointers(), Ok(()));
+        assert!(res.json.iter().filter(|e| !e.pointer.pointer.is_empty()).all(|e| e.pointer.depth + 1 == e.pointer.computed_depth()));
+    }
+
+    #[test]
+    fn raw_data_with_nested_container_in_same_chunk() {
+        let json = r#"{"skip": [1, {"a": [2]}], "obj": {"a":{}, "b": 1}, "version": "2"}"#;
+        let res = JSONParser::parse(json, ParseOptions::default().max_depth(1)).unwrap();
+        let vec = &res.json;
+        assert_eq!(vec[0].value, Some(r#"[1, {"a": [2]}]"#));
+        assert_eq!(vec[1].value, Some(r#"{"a":{}, "b": 1}"#));
+        assert_eq!(vec[2].pointer.pointer, "/version");
+    }
+
+    #[test]
+    fn raw_data_with_brackets_in_strings() {
+        let json = r#"[{"a": "{", "b": ["]", "\"}["]}, {"c": "}"}]"#;
+        let res = JSONParser::parse(json, ParseOptions::default().max_depth(1)).unwrap();
+        let vec = &res.json;
+        assert_eq!(vec[1].value, Some(r#"{"a": "{", "b": ["]", "\"}["]}"#));
+        assert_eq!(vec[2].value, Some(r#"{"c": "}"}"#));
+    }
+
+    #[test]
+    fn duplicate_key_last_value_first_position() {
+        let json = r#"{"a":1,"b":2,"a":3}"#;
+        let res = JSONParser::parse(json, ParseOptions::default()).unwrap();
+        assert_eq!(res.json.len(), 3);
+
+        let res = JSONParser::parse(json, ParseOptions::default().duplicate_key_policy(DuplicateKeyPolicy::LastValueFirstPosition)).unwrap();
+        let vec = &res.json;
+        assert_eq!(vec.len(), 2);
+        assert_eq!(vec[0].pointer.pointer, "/a");
+        assert_eq!(vec[0].value, Some("3"));
+        assert_eq!(vec[0].pointer.position, 1);
+        assert_eq!(vec[1].pointer.pointer, "/b");
+
+        let json = r#"{"a": {"x": 1, "y": 2}, "b": {"c": 1, "c": [true]}, "a": {"z": null}}"#;
+        let res = JSONParser::parse(json, ParseOptions::default().duplicate_key_policy(DuplicateKeyPolicy::LastValueFirstPosition)).unwrap();
+        let entries: Vec<(&str, usize)> = res.json.iter().map(|e| (e.pointer.pointer.as_str(), e.pointer.position)).collect();
+        assert_eq!(entries, vec![("/a", 1), ("/a/z", 9), ("/b", 4), ("/b/c", 5), ("/b/c/0", 7)]);
+    }
+
+    #[test]
+    fn max_steps() {
+        let json = r#"{"a": 1, "b": [1, 2, 3], "c": {"d": true}}"#;
+        assert!(JSONParser::parse(json, ParseOptions::default().max_steps(7)).is_ok());
+        let err = JSONParser::parse(json, ParseOptions::default().max_steps(6)).unwrap_err();
+        assert!(err.is_limit_exceeded());
+        assert_eq!(err, ParseError::LimitExceeded { limit: "max_steps", offset: 36 });
+        assert!(JSONParser::parse(json, ParseOptions::default().max_depth(1).max_steps(4)).is_ok());
+    }
+
+    #[test]
+    fn escape_sequences() {
+        let valid = [r#"\""#, r"\\", r"\/", r"\b", r"\f", r"\n", r"\r", r"\t", r"\u00e9", r"\uD83D\uDE00"];
+        for escape in valid {
+            let json = format!(r#"{{"k{}": "a{}b"}}"#, escape, escape);
+            let res = JSONParser::parse(&json, ParseOptions::default()).unwrap();
+            assert_eq!(res.json[0].pointer.pointer, format!("/k{}", escape));
+            assert_eq!(res.json[0].value, Some(format!("a{}b", escape).as_str()));
+        }
+        let invalid = [(r#"{"a": "\x41"}"#, 7), (r#"{"a": "b\q"}"#, 8), (r#"{"\q": 1}"#, 2), (r#"{"a": "\u12"}"#, 7), (r#"{"a": "\u12g4"}"#, 7), (r#"["\\", "\a"]"#, 8)];
+        for (json, offset) in invalid {
+            assert_eq!(JSONParser::parse(json, ParseOptions::default()).unwrap_err(), ParseError::InvalidEscape { offset }, "{}", json);
+            assert!(JSONParser::parse(json, ParseOptions::default().deny_unknown_escape(false)).is_ok(), "{}", json);
+        }
+        let res = JSONParser::parse(r#"{"a": "b\\", "c": "d\\\"e"}"#, ParseOptions::default()).unwrap();
+        assert_eq!(res.json[0].value, Some(r"b\\"));
+        assert_eq!(res.json[1].value, Some(r#"d\\\"e"#));
+
+        // tolerated escapes are read as the escaped character, other escapes are kept
+        let json = r#"{"k\q": "\x41\n\q\\\u12g4", "o": {"\é": "\é"}}"#;
+        let options = ParseOptions::default().deny_unknown_escape(false);
+        let res = JSONParser::parse_owned(json, options.clone()).unwrap();
+        let entries: Vec<(&str, Option<&str>)> = res.json.iter().map(|e| (e.pointer.pointer.as_str(), e.value.as_deref())).collect();
+        assert_eq!(entries, vec![("/kq", Some(r"x41\nq\\u12g4")), ("/o", Some(r#"{"\é": "\é"}"#)), ("/o/é", Some("é"))]);
+        let res = JSONParser::parse(json, options.clone()).unwrap();
+        assert_eq!((res.json[0].pointer.pointer.as_str(), res.json[0].value), ("/kq", Some(r"\x41\n\q\\\u12g4")));
+        let mut res = JSONParser::parse_owned(json, options.clone().max_depth(1)).unwrap();
+        JSONParser::change_depth_owned(&mut res, options.clone()).unwrap();
+        assert_eq!(res.json[2].value.as_deref(), Some("é"));
+        let res = JSONParser::parse_projection(json, &["/kq", "/o/é"], options).unwrap();
+        assert_eq!(res.json.iter().map(|e| e.pointer.pointer.as_str()).collect::<Vec<_>>(), vec!["/kq", "/o/é"]);
+    }
+
+    #[test]
+    fn long_number() {
+        let number = "9".repeat(10 * 1024 * 1024);
+        let json = format!(r#"{{"a": {}, "b": -1.5}}"#, number);
+        let res = JSONParser::parse(&json, ParseOptions::default()).unwrap();
+        assert_eq!(res.json[0].value.map(str::len), Some(number.len()));
+        assert_eq!(res.json[1].value, Some("-1.5"));
+
+        let err = JSONParser::parse(&json, ParseOptions::default().max_number_length(64)).unwrap_err();
+        assert!(err.is_number_too_long());
+        assert_eq!(err.offset(), 6);
+        assert!(JSONParser::parse(r#"{"a": -1.5}"#, ParseOptions::default().max_number_length(4)).is_ok());
+        assert!(JSONParser::parse(r#"[-1.55]"#, ParseOptions::default().max_number_length(4)).unwrap_err().is_number_too_long());
+    }
+
+    #[test]
+    fn strict_numbers() {
+        let json = r#"{"a": 01, "b": 1.5}"#;
+        let res = JSONParser::parse(json, ParseOptions::default()).unwrap();
+        assert_eq!(res.json.iter().map(|e| e.value).collect::<Vec<_>>(), vec![Some("01"), Some("1.5")]);
+        assert_eq!(JSONParser::parse(json, ParseOptions::default().strict_numbers(true)).unwrap_err(), ParseError::InvalidNumber { offset: 6 });
+
+        let res = JSONParser::parse(r#"[1e5, -1.5E-3]"#, ParseOptions::default().strict_numbers(true)).unwrap();
+        assert_eq!(res.json.iter().map(|e| e.value).collect::<Vec<_>>(), vec![None, Some("1e5"), Some("-1.5E-3")]);
+        assert!(JSONParser::parse(r#"[1e5]"#, ParseOptions::default()).is_err());
+        assert_eq!(JSONParser::parse(r#"[+1]"#, ParseOptions::default().strict_numbers(true)).unwrap_err(), ParseError::InvalidNumber { offset: 1 });
+    }
+
+    #[test]
+    fn key_transform() {
+        let json = r#"{"first name": "a", "@attrs": {"@id": 1, "last name": "b"}, "first_name": "c"}"#;
+        let options = ParseOptions::default().key_transform(Arc::new(|key: &str| match key.strip_prefix('@') {
+            Some(key) => Cow::Borrowed(key),
+            None if key.contains(' ') => Cow::Owned(key.replace(' ', "_")),
+            None => Cow::Borrowed(key),
+        }));
+        let res = JSONParser::parse(json, options.clone()).unwrap();
+        let pointers: Vec<&str> = res.json.iter().map(|e| e.pointer.pointer.as_str()).collect();
+        assert_eq!(pointers, vec!["/first_name", "/attrs", "/attrs/id", "/attrs/last_name", "/first_name"]);
+
+        let res = JSONParser::parse(json, options.duplicate_key_policy(DuplicateKeyPolicy::LastValueFirstPosition)).unwrap();
+        assert_eq!(res.json[0].pointer.pointer, "/first_name");
+        assert_eq!(res.json[0].value, Some("c"));
+        assert_eq!(res.json.len(), 4);
+    }
+
+    #[test]
+    fn truncate_string_values() {
+        let json = r#"{"short": "abc", "long": "abcdefgh", "accents": "ééé", "escape": "ab\u00e9cd", "n": 123456}"#;
+        let res = JSONParser::parse(json, ParseOptions::default().truncate_string_values(Some(5))).unwrap();
+        let values: Vec<(&str, Option<&str>, Option<usize>)> = res.json.iter().map(|e| (e.pointer.pointer.as_str(), e.value, e.pointer.original_len)).collect();
+        assert_eq!(values, vec![
+            ("/short", Some("abc"), None),
+            ("/long", Some("abcde"), Some(8)),
+            ("/accents", Some("éé"), Some(6)),
+            ("/escape", Some("ab"), Some(10)),
+            ("/n", Some("123456"), None),
+        ]);
+        let res = JSONParser::parse_owned(json, ParseOptions::default().truncate_string_values(Some(5))).unwrap();
+        assert_eq!(res.json[0].value.as_deref(), Some("abc"));
+        assert_eq!(res.json[1].value.as_deref(), Some("abcde…"));
+        let res = JSONParser::parse_owned_with_pool(json, ParseOptions::default().truncate_string_values(Some(5)), &mut KeyPool::new()).unwrap();
+        assert_eq!(res.find_node_at("/long").unwrap().value.as_deref(), Some("abcde…"));
+        let res = JSONParser::parse_projection(json, &["/long"], ParseOptions::default().truncate_string_values(Some(2))).unwrap();
+        assert_eq!((res.json[0].value, res.json[0].pointer.original_len), (Some("ab"), Some(8)));
+        assert!(JSONParser::parse(json, ParseOptions::default()).unwrap().json.iter().all(|e| e.pointer.original_len.is_none()));
+    }
+
+    #[test]
+    fn trim_string_values() {
+        let json = "{\" key \": \"  a b\\t \", \"n\": 1, \"arr\": [\"\\u00a0c\\n\", \"\\u00a0\u{a0}d\u{a0}\"]}";
+        let values = |options: ParseOptions| JSONParser::parse(json, options).unwrap().json.iter().map(|e| (e.pointer.pointer.clone(), e.value.map(str::to_string))).collect::<Vec<_>>();
+        let default = values(ParseOptions::default());
+        assert_eq!(default[0], ("/ key ".to_string(), Some("  a b\\t ".to_string())));
+        let trimmed = values(ParseOptions::default().trim_string_values(true));
+        assert_eq!(trimmed[0], ("/ key ".to_string(), Some("a b\\t".to_string())));
+        assert_eq!(trimmed[1].1, Some("1".to_string()));
+        assert_eq!(trimmed[3].1, Some("\\u00a0c\\n".to_string()));
+        assert_eq!(trimmed[4].1, Some("\\u00a0\u{a0}d\u{a0}".to_string()));
+        let trimmed = values(ParseOptions::default().string_trim(StringTrim::Unicode));
+        assert_eq!(trimmed[4].1, Some("\\u00a0\u{a0}d".to_string()));
+    }
+
+    #[test]
+    fn envelope_only() {
+        let json = r#"{"status": "ok", "data": [{"id": 1, "tags": ["a"]}, {"id": 2}], "meta": {"page": {"n": 1}}, "empty": {}}"#;
+        let res = JSONParser::parse(json, ParseOptions::default().envelope_only(true).keep_object_raw_data(false).max_depth(5)).unwrap();
+        let entries: Vec<(&str, ValueType, u8, Option<&str>)> = res.json.iter().map(|e| (e.pointer.pointer.as_str(), e.pointer.value_type, e.pointer.depth, e.value)).collect();
+        assert_eq!(entries, vec![
+            ("", ValueType::Object(true), 0, None),
+            ("/status", ValueType::String, 1, Some("ok")),
+            ("/data", ValueType::Array(1), 1, Some(r#"[{"id": 1, "tags": ["a"]}, {"id": 2}]"#)),
+            ("/meta", ValueType::Object(false), 1, Some(r#"{"page": {"n": 1}}"#)),
+            ("/empty", ValueType::Object(false), 1, Some("{}")),
+        ]);
+        assert!(res.validate_pointers().is_ok());
+
+        let res = JSONParser::parse(r#"[[1, 2], {"a": 1}, 3]"#, ParseOptions::default().envelope_only(true)).unwrap();
+        let entries: Vec<(&str, ValueType, Option<&str>)> = res.json.iter().map(|e| (e.pointer.pointer.as_str(), e.pointer.value_type, e.value)).collect();
+        assert_eq!(entries, vec![("", ValueType::Array(3), None), ("/0", ValueType::Array(1), Some("[1, 2]")), ("/1", ValueType::Object(false), Some(r#"{"a": 1}"#)), ("/2", ValueType::Number, Some("3"))]);
+    }
+
+    #[test]
+    fn array_index_base() {
+        let json = r#"{"items": [{"id": 1, "tags": ["a", "b"]}, {"id": 2}], "empty": []}"#;
+        let res = JSONParser::parse(json, ParseOptions::default().array_index_base(1)).unwrap();
+        let pointers: Vec<(&str, ValueType)> = res.json.iter().map(|e| (e.pointer.pointer.as_str(), e.pointer.value_type)).collect();
+        assert_eq!(pointers, vec![
+            ("/items", ValueType::Array(2)),
+            ("/items/1", ValueType::Object(true)),
+            ("/items/1/id", ValueType::Number),
+            ("/items/1/tags", ValueType::Array(2)),
+            ("/items/1/tags/1", ValueType::String),
+            ("/items/1/tags/2", ValueType::String),
+            ("/items/2", ValueType::Object(true)),
+            ("/items/2/id", ValueType::Number),
+            ("/empty", ValueType::Array(1)),
+        ]);
+        let res = JSONParser::parse(json, ParseOptions::default().array_index_base(1).start_parse_at("/items/2".to_string())).unwrap();
+        assert!(res.json.iter().any(|e| e.pointer.pointer == "/items/2/id" && e.value == Some("2")));
+        assert!(!res.json.iter().any(|e| e.pointer.pointer == "/items/1/id"));
+        let res = JSONParser::parse("[true]", ParseOptions::default().array_index_base(1)).unwrap();
+        assert_eq!(res.json[1].pointer.pointer, "/1");
+    }
+
+    #[test]
+    fn emit_keys() {
+        let json = r#"{"id": 1, "a b": {"c": [{"d": null}]}, "e": []}"#;
+        let res = JSONParser::parse(json, ParseOptions::default()).unwrap();
+        assert!(res.json.iter().all(|e| e.pointer.key_span.is_none()));
+        let res = JSONParser::parse(json, ParseOptions::default().emit_keys(true).key_transform(Arc::new(|key: &str| Cow::Owned(key.replace(' ', "_"))))).unwrap();
+        let spans: Vec<(&str, Option<&str>)> = res.json.iter().map(|e| (e.pointer.pointer.as_str(), e.pointer.key_span.clone().map(|span| &json[span]))).collect();
+        assert_eq!(spans, vec![
+            ("/id", Some("id")),
+            ("/a_b", Some("a b")),
+            ("/a_b/c", Some("c")),
+            ("/a_b/c/0", None),
+            ("/a_b/c/0/d", Some("d")),
+            ("/e", Some("e")),
+        ]);
+        let res = JSONParser::parse(json, ParseOptions::default().emit_keys(true).emit_containers(false)).unwrap();
+        let spans: Vec<(&str, Option<&str>)> = res.json.iter().map(|e| (e.pointer.pointer.as_str(), e.pointer.key_span.clone().map(|span| &json[span]))).collect();
+        assert_eq!(spans, vec![("/id", Some("id")), ("/a b/c/0/d", Some("d"))]);
+    }
+
+    #[test]
+    fn number_format() {
+        // '.' as thousands separator, groups of 3 digits
+        let thousands = NumberFormat::Custom(Arc::new(|number: &str| {
+            let digits = number.strip_prefix('-').unwrap_or(number);
+            let mut groups = digits.split('.');
+            let first = groups.next().filter(|first| (1..=3).contains(&first.len()))?;
+            let rest: Vec<&str> = groups.collect();
+            if rest.is_empty() {
+                return Some(Cow::Borrowed(number));
+            }
+            if !first.bytes().chain(rest.iter().flat_map(|group| group.bytes())).all(|b| b.is_ascii_digit()) || rest.iter().any(|group| group.len() != 3) {
+                return None;
+            }
+            Some(Cow::Owned(number.replace('.', "")))
+        }));
+        let json = r#"{"a": 1.234.567, "b": -12.000, "c": [1,5], "d": "1.234,56", "e": 7}"#;
+        assert!(JSONParser::parse(json, ParseOptions::default()).unwrap_err().is_invalid_number());
+        let res = JSONParser::parse(json, ParseOptions::default().number_format(thousands.clone())).unwrap();
+        assert_eq!(res.json[0].value, Some("1.234.567"));
+        let res = JSONParser::parse_owned(json, ParseOptions::default().number_format(thousands.clone())).unwrap();
+        let values: Vec<(&str, Option<&str>)> = res.json.iter().map(|e| (e.pointer.pointer.as_str(), e.value.as_deref())).collect();
+        assert_eq!(values, vec![("/a", Some("1234567")), ("/b", Some("-12000")), ("/c", None), ("/c/0", Some("1")), ("/c/1", Some("5")), ("/d", Some("1.234,56")), ("/e", Some("7"))]);
+        let mut res = JSONParser::parse_owned(r#"{"a": {"b": 1.000}}"#, ParseOptions::default().number_format(thousands.clone()).max_depth(1)).unwrap();
+        JSONParser::change_depth_owned(&mut res, ParseOptions::default().number_format(thousands.clone()).max_depth(2)).unwrap();
+        assert_eq!(res.json[1].value.as_deref(), Some("1000"));
+        let err = JSONParser::parse(r#"{"a": 1.5}"#, ParseOptions::default().number_format(thousands)).unwrap_err();
+        assert_eq!(err, ParseError::InvalidNumber { offset: 6 });
+
+        // the recognizer runs once per number
+        let calls = Arc::new(Mutex::new(0));
+        let counted = calls.clone();
+        let counting = NumberFormat::Custom(Arc::new(move |number: &str| {
+            *counted.lock().unwrap() += 1;
+            Some(Cow::Owned(number.replace('.', "")))
+        }));
+        let res = JSONParser::parse_owned(json, ParseOptions::default().number_format(counting)).unwrap();
+        assert_eq!(res.json[0].value.as_deref(), Some("1234567"));
+        assert_eq!(*calls.lock().unwrap(), 5);
+    }
+
+    #[test]
+    fn max_open_containers() {
+        let nested = "[".repeat(100) + &"]".repeat(100);
+        let options = ParseOptions::default().max_depth(u8::MAX);
+        assert!(JSONParser::parse(&nested, options.clone()).is_ok());
+        let err = JSONParser::parse(&nested, options.clone().max_open_containers(50)).unwrap_err();
+        assert_eq!(err, ParseError::LimitExceeded { limit: "max_open_containers", offset: 50 });
+        assert!(JSONParser::parse(&nested, options.clone().max_open_containers(100)).is_ok());
+        let err = JSONParser::parse(r#"{"a": {"b": [{"c": 1}]}}"#, options.clone().max_open_containers(3)).unwrap_err();
+        assert_eq!(err, ParseError::LimitExceeded { limit: "max_open_containers", offset: 13 });
+        assert!(JSONParser::parse(r#"{"a": {"b": [{"c": 1}]}}"#, options.clone().max_open_containers(4)).is_ok());
+        assert!(JSONParser::parse("[]", options.max_open_containers(0)).unwrap_err().is_limit_exceeded());
+    }
+
+    #[test]
+    fn annotate_roles() {
+        let json = r#"{"a": [1, {"b": [[2]]}], "c": {"d": null}}"#;
+        let res = JSONParser::parse(json, ParseOptions::default().annotate_roles(true)).unwrap();
+        let roles: Vec<(&str, Option<PointerRole>)> = res.json.iter().map(|entry| (entry.pointer.pointer.as_str(), entry.pointer.role)).collect();
+        assert_eq!(roles, vec![
+            ("/a", Some(PointerRole::ObjectMember)), ("/a/0", Some(PointerRole::ArrayElement)), ("/a/1", Some(PointerRole::ArrayElement)),
+            ("/a/1/b", Some(PointerRole::ObjectMember)), ("/a/1/b/0", Some(PointerRole::ArrayElement)), ("/a/1/b/0/0", Some(PointerRole::ArrayElement)),
+            ("/c", Some(PointerRole::ObjectMember)), ("/c/d", Some(PointerRole::ObjectMember)),
+        ]);
+        assert_eq!(res.validate_pointers(), Ok(()));
+        assert!(JSONParser::parse(json, ParseOptions::default()).unwrap().json.iter().all(|entry| entry.pointer.role.is_none()));
+
+        let res = JSONParser::parse("[1, [2]]", ParseOptions::default().annotate_roles(true).prefix("/root".to_string())).unwrap();
+        assert_eq!(res.json.iter().map(|entry| entry.pointer.role).collect::<Vec<_>>(), vec![Some(PointerRole::RootArray), Some(PointerRole::ArrayElement), Some(PointerRole::ArrayElement), Some(PointerRole::ArrayElement)]);
+        let res = JSONParser::parse(json, ParseOptions::default().annotate_roles(true).envelope_only(true)).unwrap();
+        assert_eq!(res.json[0].pointer.role, Some(PointerRole::RootObject));
+
+        let mut res = JSONParser::parse(json, ParseOptions::default().annotate_roles(true).max_depth(1)).unwrap();
+        JSONParser::change_depth(&mut res, ParseOptions::default().annotate_roles(true).max_depth(3)).unwrap();
+        assert!(res.json.iter().all(|entry| entry.pointer.role.is_some()));
+        assert_eq!(res.json.iter().find(|entry| entry.pointer.pointer == "/c/d").unwrap().pointer.role, Some(PointerRole::ObjectMember));
+
+        let mut misclassified = JSONParser::parse(json, ParseOptions::default().annotate_roles(true)).unwrap();
+        misclassified.json[3].pointer.role = Some(PointerRole::ArrayElement);
+        assert!(misclassified.validate_pointers().unwrap_err().contains("has role ArrayElement"));
+    }
+
+    #[test]
+    fn progress() {
+        let json = format!("[{}]", vec![r#"{"id": 1, "name": "abcdefgh"}"#; 1000].join(", "));
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let recorded = calls.clone();
+        let options = ParseOptions::default().progress_interval(4096).progress(Arc::new(move |consumed, total| recorded.lock().unwrap().push((consumed, total))));
+        JSONParser::parse(&json, options.clone()).unwrap();
+        let calls = calls.lock().unwrap().clone();
+        assert_eq!(calls.last(), Some(&(json.len(), json.len())));
+        assert!(calls.iter().all(|(_, total)| *total == json.len()));
+        assert!(calls.windows(2).all(|pair| pair[1].0 >= pair[0].0 + 4096 || pair[1].0 == json.len()));
+        assert!(calls.len() >= json.len() / 4096 && calls.len() <= json.len() / 4096 + 2);
+
+        let streamed = Arc::new(Mutex::new(Vec::new()));
+        let recorded = streamed.clone();
+        let options = ParseOptions::default().progress_interval(4096).progress(Arc::new(move |consumed, total| recorded.lock().unwrap().push((consumed, total))));
+        JSONParser::validate_stream_with_options(json.as_bytes(), &options).unwrap();
+        let streamed = streamed.lock().unwrap().clone();
+        assert_eq!(streamed.last(), Some(&(json.len(), 0)));
+        assert!(streamed.len() > 1);
+    }
+
+    #[test]
+    fn key_count() {
+        let json = r#"{"a": {"b": 1, "c": {"d": {"e": 1, "f": 2}}}, "g": {}, "h": [{"i": 1, "i": 2}]}"#;
+        let res = JSONParser::parse(json, ParseOptions::default().max_depth(3)).unwrap();
+        let key_count = |res: &crate::ParseResult<&str>, pointer: &str| res.json.iter().find(|entry| entry.pointer.pointer == pointer).unwrap().pointer.key_count;
+        assert_eq!(key_count(&res, "/a"), Some(2));
+        assert_eq!(key_count(&res, "/a/c"), Some(1));
+        // at max_depth, not expanded but its keys are read
+        assert_eq!(res.json.iter().find(|entry| entry.pointer.pointer == "/a/c/d").unwrap().pointer.value_type, ValueType::Object(false));
+        assert_eq!(key_count(&res, "/a/c/d"), Some(2));
+        assert_eq!(key_count(&res, "/g"), Some(0));
+        assert_eq!(key_count(&res, "/h/0"), Some(2));
+        assert_eq!(key_count(&res, "/a/b"), None);
+        assert_eq!(key_count(&res, "/h"), None);
+
+        let res = JSONParser::parse(json, ParseOptions::default().duplicate_key_policy(DuplicateKeyPolicy::LastValueFirstPosition)).unwrap();
+        assert_eq!(key_count(&res, "/h/0"), Some(1));
+        let res = JSONParser::parse(json, ParseOptions::default().envelope_only(true)).unwrap();
+        assert_eq!(key_count(&res, ""), Some(3));
+        assert_eq!(key_count(&res, "/a"), Some(2));
+    }
+}
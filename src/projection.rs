@@ -0,0 +1,474 @@
+use crate::error::ParseError;
+use crate::lexer::{find_invalid_escape, Lexer, Token};
+use crate::parser::Parser;
+use crate::string_subtype::StringSubtype;
+use crate::{FlatJsonValue, JsonArrayEntries, JSONParser, ParseOptions, ParseResult, PointerFragment, PointerKey, ValueType};
+
+impl JSONParser {
+    /// Extract only values at `pointers`, e.g. `/users/*/email`, where `*` matches any array index, other index segments being canonical: `01` or `+1` match no element. Missing pointers are absent from the result.
+    /// The document is navigated token by token and every value which is not on the way of a pointer is skipped without being flattened,
+    /// so this is much faster than a full parse when few fields are needed. A matched object or array is flattened along with its children, up to `max_depth`.
+    /// `start_parse_at` and `prefix` options are ignored, though checked by `ParseOptions::validate`.
+    pub fn parse_projection<'json>(input: &'json str, pointers: &[&str], options: ParseOptions) -> Result<ParseResult<&'json str>, ParseError> {
+        options.validate().map_err(|reason| ParseError::InvalidOptions { reason, offset: 0 })?;
+        let patterns: Vec<Vec<&str>> = pointers.iter()
+            .map(|pointer| pointer.strip_prefix('/').unwrap_or(pointer))
+            .map(|pointer| if pointer.is_empty() { vec![] } else { pointer.split('/').collect() })
+            .collect();
+        let options = ParseOptions { start_parse_at: None, prefix: None, progress: None, ..options };
+        if patterns.iter().any(|pattern| pattern.is_empty()) {
+            return JSONParser::parse(input, options);
+        }
+        let mut walker = ProjectionWalker { lexer: Lexer::new(input.as_bytes()).strict_numbers(options.strict_numbers), patterns, options: &options, target: Vec::with_capacity(16), position: 0, max_json_depth: 0 };
+        let candidates: Vec<usize> = (0..walker.patterns.len()).collect();
+        let mut route = PointerFragment::with_capacity(128);
+        let token = walker.lexer.next_token();
+        let start = walker.lexer.token_start();
+        match token {
+            Some(Token::CurlyOpen) => walker.walk_object(&mut route, 0, &candidates)?,
+            Some(Token::SquareOpen) => walker.walk_array(&mut route, 0, &candidates)?,
+            token => return Err(walker.unexpected(token, "'{' or '['")),
+        }
+        Ok(ParseResult {
+            json: walker.target,
+            max_json_depth: walker.max_json_depth,
+            parsing_max_depth: options.max_depth,
+            started_parsing_at: None,
+            parsing_prefix: None,
+            depth_after_start_at: 0,
+            source_span: start..walker.lexer.reader_index(),
+        })
+    }
+
+    /// Type and raw value at `pointer`, e.g. `/data/0/id`, or `None` when there is no value there. `""` is the root value.
+    /// Like `parse_projection`, values which are not on the way are skipped without being flattened, and reading stops once the value is found, so nothing is validated beyond it.
+    /// The pointer is exact: there is no wildcard. A container is returned as its raw data, typed as if it was beyond `max_depth`: `Object(false)` or `Array(1)`.
+    /// `null` is returned with `"null"` as raw value. Index segments are the canonical ones of RFC 6901: `01` or `+1` never match an element.
+    pub fn parse_value_at<'json>(input: &'json str, pointer: &str, options: ParseOptions) -> Result<Option<(ValueType, &'json str)>, ParseError> {
+        options.validate().map_err(|reason| ParseError::InvalidOptions { reason, offset: 0 })?;
+        let mut walker = ProjectionWalker { lexer: Lexer::new(input.as_bytes()).strict_numbers(options.strict_numbers), patterns: vec![], options: &options, target: vec![], position: 0, max_json_depth: 0 };
+        match walker.find_value(pointer)? {
+            Some(token) => walker.raw_value(token).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Elements of the array at `prefix`, `""` being the root array, for which `predicate` holds, e.g. the rows of a huge export having an `active` status.
+    /// Elements are flattened one at a time, like with `parse_as_element`, and entries of rejected ones are dropped right away, so memory is bounded by matching elements.
+    /// `index` of kept elements is their index in the array. Like `parse_value_at`, the document is not flattened outside of the array, and an absent or non array `prefix` gives no element.
+    pub fn parse_array_filtered<'json>(input: &'json str, prefix: &str, predicate: impl Fn(&JsonArrayEntries<&'json str>) -> bool, options: ParseOptions) -> Result<Vec<JsonArrayEntries<&'json str>>, ParseError> {
+        let mut matching = Vec::new();
+        JSONParser::for_each_array_element(input, prefix, options, |entries| {
+            if predicate(&entries) {
+                matching.push(entries);
+            }
+        })?;
+        Ok(matching)
+    }
+
+    /// Flatten elements of the array at `prefix` one at a time, like `parse_as_element`, handing each one to `f`; an absent or non array `prefix` gives no element.
+    pub(crate) fn for_each_array_element<'json>(input: &'json str, prefix: &str, options: ParseOptions, mut f: impl FnMut(JsonArrayEntries<&'json str>)) -> Result<(), ParseError> {
+        options.validate().map_err(|reason| ParseError::InvalidOptions { reason, offset: 0 })?;
+        let prefix = if prefix == "/" { "" } else { prefix };
+        let mut walker = ProjectionWalker { lexer: Lexer::new(input.as_bytes()).strict_numbers(options.strict_numbers), patterns: vec![], options: &options, target: vec![], position: 0, max_json_depth: 0 };
+        if !matches!(walker.find_value(prefix)?, Some(Token::SquareOpen)) {
+            return Ok(());
+        }
+        let mut token = walker.lexer.next_token();
+        if matches!(token, Some(Token::SquareClose)) {
+            return Ok(());
+        }
+        let mut index = 0_usize;
+        loop {
+            let value = token.ok_or(ParseError::UnexpectedEof { offset: walker.lexer.reader_index() })?;
+            let start = walker.lexer.token_start();
+            walker.skip_value(value);
+            let element = &input[start..walker.lexer.reader_index()];
+            let res = JSONParser::parse_as_element(element, prefix, index, options.clone()).map_err(|e| e.offset_by(start))?;
+            f(JsonArrayEntries { entries: res.json, index });
+            match walker.lexer.next_token() {
+                Some(Token::Comma) => token = walker.lexer.next_token(),
+                Some(Token::SquareClose) => return Ok(()),
+                token => return Err(walker.unexpected(token, "',' or ']' after array value")),
+            }
+            index += 1;
+        }
+    }
+}
+
+struct ProjectionWalker<'a, 'json> {
+    lexer: Lexer<'json>,
+    // segments of each pointer, without the leading '/'
+    patterns: Vec<Vec<&'a str>>,
+    options: &'a ParseOptions,
+    target: Vec<FlatJsonValue<&'json str>>,
+    position: usize,
+    max_json_depth: usize,
+}
+
+impl<'a, 'json> ProjectionWalker<'a, 'json> {
+    /// `candidates` are patterns matching `route` so far, `depth` being the number of segments of `route`.
+    fn walk_value(&mut self, token: Token<'json>, route: &mut PointerFragment, depth: usize, candidates: &[usize]) -> Result<(), ParseError> {
+        if candidates.iter().any(|i| self.patterns[*i].len() == depth) {
+            return self.emit(token, route, depth);
+        }
+        match token {
+            Token::CurlyOpen => self.walk_object(route, depth, candidates),
+            Token::SquareOpen => self.walk_array(route, depth, candidates),
+            _ => Ok(())
+        }
+    }
+
+    fn walk_object(&mut self, route: &mut PointerFragment, depth: usize, candidates: &[usize]) -> Result<(), ParseError> {
+        let mut token = self.lexer.next_token();
+        loop {
+            let key = match token {
+                Some(Token::String(key)) => {
+                    self.check_escapes(key)?;
+                    self.options.key_segment(key)
+                }
+                Some(Token::CurlyClose) => return Ok(()),
+                token => return Err(self.unexpected(token, "object key")),
+            };
+            match self.lexer.next_token() {
+                Some(Token::Colon) => {}
+                token => return Err(self.unexpected(token, "':' after object key")),
+            }
+            let value = self.next_value()?;
+            if candidates.iter().any(|i| self.patterns[*i][depth] == key) {
+                let matching: Vec<usize> = candidates.iter().copied().filter(|i| self.patterns[*i][depth] == key).collect();
+                route.push_key(&key);
+                self.walk_value(value, route, depth + 1, &matching)?;
+                route.pop();
+            } else {
+                self.skip_value(value);
+            }
+            match self.lexer.next_token() {
+                Some(Token::Comma) => token = self.lexer.next_token(),
+                Some(Token::CurlyClose) => return Ok(()),
+                token => return Err(self.unexpected(token, "',' or '}' after object value")),
+            }
+        }
+    }
+
+    fn walk_array(&mut self, route: &mut PointerFragment, depth: usize, candidates: &[usize]) -> Result<(), ParseError> {
+        let mut token = self.lexer.next_token();
+        if matches!(token, Some(Token::SquareClose)) {
+            return Ok(());
+        }
+        let mut index = 0_usize;
+        loop {
+            let value = token.ok_or(ParseError::UnexpectedEof { offset: self.lexer.reader_index() })?;
+            let rendered_index = index + self.options.array_index_base;
+            let matches_index = |segment: &str| segment == "*" || index_segment(segment) == Some(rendered_index);
+            if candidates.iter().any(|i| matches_index(self.patterns[*i][depth])) {
+                let matching: Vec<usize> = candidates.iter().copied().filter(|i| matches_index(self.patterns[*i][depth])).collect();
+                route.push_index(rendered_index);
+                self.walk_value(value, route, depth + 1, &matching)?;
+                route.pop();
+            } else {
+                self.skip_value(value);
+            }
+            match self.lexer.next_token() {
+                Some(Token::Comma) => token = self.lexer.next_token(),
+                Some(Token::SquareClose) => return Ok(()),
+                token => return Err(self.unexpected(token, "',' or ']' after array value")),
+            }
+            index += 1;
+        }
+    }
+
+    // first token of the value at `pointer`, an exact pointer, the lexer being at the start of the document
+    fn find_value(&mut self, pointer: &str) -> Result<Option<Token<'json>>, ParseError> {
+        let mut token = match self.lexer.next_token() {
+            Some(token) if matches!(token, Token::CurlyOpen | Token::SquareOpen) => token,
+            token => return Err(self.unexpected(token, "'{' or '['")),
+        };
+        let mut segments = pointer.strip_prefix('/').map(|pointer| pointer.split('/'));
+        while let Some(segment) = segments.as_mut().and_then(Iterator::next) {
+            let value = match token {
+                Token::CurlyOpen => self.find_member(segment)?,
+                Token::SquareOpen => match index_segment(segment).and_then(|index| index.checked_sub(self.options.array_index_base)) {
+                    Some(index) => self.find_element(index)?,
+                    None => None,
+                },
+                _ => None,
+            };
+            match value {
+                Some(value) => token = value,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(token))
+    }
+
+    // value of the member `key` of the object whose '{' has just been read, the lexer being positioned after its first token
+    fn find_member(&mut self, key: &str) -> Result<Option<Token<'json>>, ParseError> {
+        let mut token = self.lexer.next_token();
+        loop {
+            let member = match token {
+                Some(Token::String(member)) => {
+                    self.check_escapes(member)?;
+                    self.options.key_segment(member)
+                }
+                Some(Token::CurlyClose) => return Ok(None),
+                token => return Err(self.unexpected(token, "object key")),
+            };
+            match self.lexer.next_token() {
+                Some(Token::Colon) => {}
+                token => return Err(self.unexpected(token, "':' after object key")),
+            }
+            let value = self.next_value()?;
+            if member == key {
+                return Ok(Some(value));
+            }
+            self.skip_value(value);
+            match self.lexer.next_token() {
+                Some(Token::Comma) => token = self.lexer.next_token(),
+                Some(Token::CurlyClose) => return Ok(None),
+                token => return Err(self.unexpected(token, "',' or '}' after object value")),
+            }
+        }
+    }
+
+    // element at `index` of the array whose '[' has just been read
+    fn find_element(&mut self, index: usize) -> Result<Option<Token<'json>>, ParseError> {
+        let mut token = self.lexer.next_token();
+        if matches!(token, Some(Token::SquareClose)) {
+            return Ok(None);
+        }
+        let mut i = 0_usize;
+        loop {
+            let value = token.ok_or(ParseError::UnexpectedEof { offset: self.lexer.reader_index() })?;
+            if i == index {
+                return Ok(Some(value));
+            }
+            self.skip_value(value);
+            match self.lexer.next_token() {
+                Some(Token::Comma) => token = self.lexer.next_token(),
+                Some(Token::SquareClose) => return Ok(None),
+                token => return Err(self.unexpected(token, "',' or ']' after array value")),
+            }
+            i += 1;
+        }
+    }
+
+    fn raw_value(&mut self, token: Token<'json>) -> Result<(ValueType, &'json str), ParseError> {
+        let eof = |lexer: &Lexer| ParseError::UnexpectedEof { offset: lexer.reader_index() };
+        match token {
+            Token::CurlyOpen => self.lexer.consume_string_until_end_of_object(true).map(|raw| (ValueType::Object(false), raw)).ok_or_else(|| eof(&self.lexer)),
+            Token::SquareOpen => {
+                let start = self.lexer.reader_index() - 1;
+                self.lexer.consume_string_until_end_of_array(start, false).map(|raw| (ValueType::Array(1), raw)).ok_or_else(|| eof(&self.lexer))
+            }
+            Token::String(value) => {
+                self.check_escapes(value)?;
+                Ok((ValueType::String, self.options.string_trim.trim(value)))
+            }
+            Token::Number(value) => {
+                if self.options.max_number_length.is_some_and(|max_number_length| value.len() > max_number_length) {
+                    return Err(ParseError::NumberTooLong { offset: self.lexer.token_start() });
+                }
+                Ok((ValueType::Number, value))
+            }
+            Token::Boolean(value) => Ok((ValueType::Bool, value)),
+            Token::Null => Ok((ValueType::Null, "null")),
+            token => Err(self.unexpected(Some(token), "value")),
+        }
+    }
+
+    fn next_value(&mut self) -> Result<Token<'json>, ParseError> {
+        self.lexer.next_token().ok_or(ParseError::UnexpectedEof { offset: self.lexer.reader_index() })
+    }
+
+    // only keys and emitted values are checked, skipped values are not
+    fn check_escapes(&self, string: &str) -> Result<(), ParseError> {
+        match find_invalid_escape(string) {
+            Some(i) if self.options.deny_unknown_escape => Err(ParseError::InvalidEscape { offset: self.lexer.token_start() + 1 + i }),
+            _ => Ok(()),
+        }
+    }
+
+    fn unexpected(&self, token: Option<Token<'json>>, expected: &'static str) -> ParseError {
+        match token {
+            Some(token) => ParseError::UnexpectedToken { expected, found: format!("{:?}", token), offset: self.lexer.token_start() },
+            None => ParseError::UnexpectedEof { offset: self.lexer.reader_index() },
+        }
+    }
+
+    fn skip_value(&mut self, token: Token<'json>) {
+        match token {
+            Token::CurlyOpen => { self.lexer.consume_string_until_end_of_object(false); }
+            Token::SquareOpen => { self.lexer.consume_string_until_end_of_array(self.lexer.reader_index() - 1, false); }
+            _ => {}
+        }
+    }
+
+    fn emit(&mut self, token: Token<'json>, route: &PointerFragment, depth: usize) -> Result<(), ParseError> {
+        let entry_depth = self.options.start_depth + depth as u8 - 1;
+        let (value_type, value) = match token {
+            Token::CurlyOpen | Token::SquareOpen => return self.emit_container(token, route, entry_depth),
+            Token::String(value) => {
+                self.check_escapes(value)?;
+                (ValueType::String, Some(self.options.string_trim.trim(value)))
+            }
+            Token::Number(value) => {
+                if self.options.max_number_length.is_some_and(|max_number_length| value.len() > max_number_length) {
+                    return Err(ParseError::NumberTooLong { offset: self.lexer.token_start() });
+                }
+                (ValueType::Number, Some(value))
+            }
+            Token::Boolean(value) => (ValueType::Bool, Some(value)),
+            Token::Null => (ValueType::Null, None),
+            token => return Err(self.unexpected(Some(token), "value")),
+        };
+        self.position += 1;
+        self.max_json_depth = self.max_json_depth.max(entry_depth as usize);
+        let mut pointer = PointerKey::from_pointer(route.as_str().to_string(), value_type, entry_depth, self.position);
+        if self.options.detect_string_subtypes && matches!(value_type, ValueType::String) {
+            pointer.string_subtype = value.and_then(StringSubtype::detect);
+        }
+        let value = match value {
+            Some(value) if matches!(value_type, ValueType::String) => {
+                let (value, original_len) = self.options.truncate_string(value);
+                pointer.original_len = original_len;
+                Some(value)
+            }
+            value => value,
+        };
+        self.target.push(FlatJsonValue { pointer, value });
+        Ok(())
+    }
+
+    // flatten the container by parsing its raw data with `route` as prefix, as `change_depth` does
+    fn emit_container(&mut self, token: Token<'json>, route: &PointerFragment, entry_depth: u8) -> Result<(), ParseError> {
+        let start = self.lexer.reader_index() - 1;
+        let raw = if matches!(token, Token::CurlyOpen) {
+            self.lexer.consume_string_until_end_of_object(true)
+        } else {
+            self.lexer.consume_string_until_end_of_array(start, false)
+        }.ok_or(ParseError::UnexpectedEof { offset: self.lexer.reader_index() })?;
+        let options = ParseOptions { prefix: Some(route.as_str().to_string()), ..self.options.clone() };
+        let mut lexer = Lexer::new(raw.as_bytes()).strict_numbers(options.strict_numbers);
+        let mut res = Parser::new(&mut lexer).parse(&options, entry_depth + 1)?;
+        self.position += 1;
+        let container_position = self.position;
+        let mut children_start = 0;
+        if let Some(root) = res.json.first().filter(|entry| entry.pointer.pointer.is_empty()) {
+            // root array entry of the sub parse is the container itself
+            let (value_type, value) = (root.pointer.value_type, root.value);
+            res.json[0] = FlatJsonValue { pointer: PointerKey::from_pointer(route.as_str().to_string(), value_type, entry_depth, container_position), value };
+            children_start = 1;
+        } else if matches!(token, Token::CurlyOpen) && self.options.emit_containers {
+            let max_depth = self.options.max_depth;
+            let value = if self.options.keep_object_raw_data || entry_depth >= max_depth { Some(raw) } else { None };
+            self.target.push(FlatJsonValue { pointer: PointerKey::from_pointer(route.as_str().to_string(), ValueType::Object(entry_depth < max_depth), entry_depth, container_position), value });
+        }
+        let mut last_position = container_position;
+        for entry in res.json[children_start..].iter_mut() {
+            entry.pointer.position += container_position;
+            last_position = last_position.max(entry.pointer.position);
+        }
+        self.position = last_position;
+        self.max_json_depth = self.max_json_depth.max(res.max_json_depth).max(entry_depth as usize);
+        self.target.extend(res.json);
+        Ok(())
+    }
+}
+
+// index of an array index segment as rendered in pointers, only a canonical one, i.e. `0` or digits without leading zero as defined by RFC 6901
+fn index_segment(segment: &str) -> Option<usize> {
+    if segment.is_empty() || !segment.bytes().all(|b| b.is_ascii_digit()) || segment.len() > 1 && segment.starts_with('0') {
+        return None;
+    }
+    segment.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::ParseError;
+    use crate::{JsonArrayEntries, JSONParser, ParseOptions, ValueType};
+
+    #[test]
+    fn parse_projection() {
+        let json = r#"{"id": 1, "meta": {"skip": [1, {"a": [2]}], "version": "2"}, "users": [{"name": "a", "email": "a@b.c", "tags": ["x"]}, {"name": "b"}, {"email": "d@e.f", "tags": []}]}"#;
+        let res = JSONParser::parse_projection(json, &["/users/*/email", "/meta/version", "/missing", "/users/*/tags"], ParseOptions::default()).unwrap();
+        let entries: Vec<(&str, ValueType, Option<&str>, u8)> = res.json.iter().map(|e| (e.pointer.pointer.as_str(), e.pointer.value_type, e.value, e.pointer.depth)).collect();
+        assert_eq!(entries, vec![
+            ("/meta/version", ValueType::String, Some("2"), 2),
+            ("/users/0/email", ValueType::String, Some("a@b.c"), 3),
+            ("/users/0/tags", ValueType::Array(1), None, 3),
+            ("/users/0/tags/0", ValueType::String, Some("x"), 4),
+            ("/users/2/email", ValueType::String, Some("d@e.f"), 3),
+            ("/users/2/tags", ValueType::Array(1), None, 3),
+        ]);
+        let positions: Vec<usize> = res.json.iter().map(|e| e.pointer.position).collect();
+        assert_eq!(positions, vec![1, 2, 3, 4, 5, 6]);
+
+        let full = JSONParser::parse(json, ParseOptions::default()).unwrap();
+        let users_1 = JSONParser::parse_projection(json, &["/users/1"], ParseOptions::default()).unwrap();
+        let expected: Vec<(&str, ValueType, u8)> = full.json.iter().filter(|e| e.pointer.pointer.starts_with("/users/1")).map(|e| (e.pointer.pointer.as_str(), e.pointer.value_type, e.pointer.depth)).collect();
+        assert_eq!(users_1.json.iter().map(|e| (e.pointer.pointer.as_str(), e.pointer.value_type, e.pointer.depth)).collect::<Vec<_>>(), expected);
+        assert_eq!(users_1.json[0].value, Some(r#"{"name": "b"}"#));
+    }
+
+    #[test]
+    fn parse_projection_root_array() {
+        let json = r#"[{"id": 1, "deep": {"a": {"b": 1}}}, {"id": 2}]"#;
+        let res = JSONParser::parse_projection(json, &["/*/id", "/0/deep"], ParseOptions::default().max_depth(3)).unwrap();
+        let pointers: Vec<(&str, ValueType)> = res.json.iter().map(|e| (e.pointer.pointer.as_str(), e.pointer.value_type)).collect();
+        assert_eq!(pointers, vec![("/0/id", ValueType::Number), ("/0/deep", ValueType::Object(true)), ("/0/deep/a", ValueType::Object(false)), ("/1/id", ValueType::Number)]);
+        assert_eq!(res.json[2].value, Some(r#"{"b": 1}"#));
+
+        assert_eq!(JSONParser::parse_projection(json, &[""], ParseOptions::default()).unwrap().json.len(), JSONParser::parse(json, ParseOptions::default()).unwrap().json.len());
+        assert!(JSONParser::parse_projection(json, &["/01/id", "/+1/id", "/1 /id"], ParseOptions::default()).unwrap().json.is_empty());
+        assert!(JSONParser::parse_projection("", &["/a"], ParseOptions::default()).is_err());
+        assert!(JSONParser::parse_projection(r#"{"a" 1}"#, &["/a"], ParseOptions::default()).is_err());
+    }
+
+    #[test]
+    fn parse_value_at() {
+        let json = r#"{"skip": {"a": [1, "}"]}, "data": [{"id": 1, "tags": ["x", "y"]}, {"id": 2, "name": "b", "none": null}], "deep": {"a": {"b": true}}}"#;
+        let value_at = |pointer: &str| JSONParser::parse_value_at(json, pointer, ParseOptions::default()).unwrap();
+        assert_eq!(value_at("/data/0/id"), Some((ValueType::Number, "1")));
+        assert_eq!(value_at("/data/1/name"), Some((ValueType::String, "b")));
+        assert_eq!(value_at("/data/1/none"), Some((ValueType::Null, "null")));
+        assert_eq!(value_at("/data/0/tags/1"), Some((ValueType::String, "y")));
+        assert_eq!(value_at("/deep/a"), Some((ValueType::Object(false), r#"{"b": true}"#)));
+        assert_eq!(value_at("/data/0/tags"), Some((ValueType::Array(1), r#"["x", "y"]"#)));
+        assert_eq!(value_at(""), Some((ValueType::Object(false), json)));
+        assert_eq!(value_at("/data/2/id"), None);
+        assert_eq!(value_at("/data/*/id"), None);
+        assert_eq!(value_at("/data/0/id/x"), None);
+        assert_eq!(value_at("/missing"), None);
+
+        assert_eq!(JSONParser::parse_value_at("[1, [2, 3]]", "/1/0", ParseOptions::default()).unwrap(), Some((ValueType::Number, "2")));
+        assert!(JSONParser::parse_value_at(r#"{"a" 1}"#, "/a", ParseOptions::default()).is_err());
+        assert!(JSONParser::parse_value_at("1", "", ParseOptions::default()).is_err());
+        assert_eq!(JSONParser::parse_value_at("[1, [2, 3]]", "/2/1", ParseOptions::default().array_index_base(1)).unwrap(), Some((ValueType::Number, "2")));
+        assert_eq!(JSONParser::parse_value_at("[1, [2, 3]]", "/0", ParseOptions::default().array_index_base(1)).unwrap(), None);
+        for pointer in ["/01/0", "/+1/0", "/1/00", "/ 1/0"] {
+            assert_eq!(JSONParser::parse_value_at("[1, [2, 3]]", pointer, ParseOptions::default()).unwrap(), None, "{}", pointer);
+        }
+        assert!(JSONParser::parse_value_at("[1]", "/0", ParseOptions::default().array_index_base(2)).unwrap_err().is_invalid_options());
+    }
+
+    #[test]
+    fn parse_array_filtered() {
+        let rows: Vec<String> = (0..10_000).map(|i| format!(r#"{{"status": "{}", "id": {}, "tags": ["t{}"]}}"#, if i % 2500 == 7 { "active" } else { "inactive" }, i, i)).collect();
+        let json = format!(r#"{{"meta": {{"count": 10000}}, "data": [{}]}}"#, rows.join(", "));
+        let is_active = |row: &JsonArrayEntries<&str>| row.find_node_at(&format!("/data/{}/status", row.index)).and_then(|entry| entry.value) == Some("active");
+        let res = JSONParser::parse_array_filtered(&json, "/data", is_active, ParseOptions::default()).unwrap();
+        assert_eq!(res.iter().map(|row| row.index).collect::<Vec<_>>(), vec![7, 2507, 5007, 7507]);
+        assert_eq!(res[1].find_node_at("/data/2507/id").unwrap().value, Some("2507"));
+        assert_eq!(res[1].find_node_at("/data/2507/tags/0").unwrap().value, Some("t2507"));
+        assert!(res[1].entries.iter().all(|entry| entry.pointer.index == 2507));
+
+        let res = JSONParser::parse_array_filtered(r#"[1, "a", [2], {"b": 3}]"#, "", |row| row.entries.len() == 1, ParseOptions::default()).unwrap();
+        assert_eq!(res.iter().map(|row| row.entries[0].pointer.pointer.as_str()).collect::<Vec<_>>(), vec!["/0", "/1"]);
+        assert!(JSONParser::parse_array_filtered(&json, "/meta", |_| true, ParseOptions::default()).unwrap().is_empty());
+        assert!(JSONParser::parse_array_filtered(&json, "/missing", |_| true, ParseOptions::default()).unwrap().is_empty());
+        let err = JSONParser::parse_array_filtered(r#"{"data": [{"a": 1}, {"a": 1.2.3}]}"#, "/data", |_| true, ParseOptions::default()).unwrap_err();
+        assert_eq!(err, ParseError::InvalidNumber { offset: 26 });
+    }
+}
@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::cmp::Ordering;
 use std::fmt::Debug;
 
@@ -41,15 +42,34 @@ macro_rules! vec_matches {
     }
 }
 
-pub fn serialize_to_json<'a, V: Debug + Clone + AsRef<str> + GetBytes>(data: &mut Vec<FlatJsonValue<V>>) -> Value<V> {
+/// Order in which members and elements are emitted by [`serialize_to_json_ordered`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Order {
+    /// Original document order, given by `PointerKey::position`, whatever the order of entries, e.g. after sorting them by pointer for lookups.
+    #[default]
+    Document,
+    /// Order of entries in the vector, positions being ignored.
+    Entries,
+}
+
+pub fn serialize_to_json<V: Debug + Clone + AsRef<str> + GetBytes>(data: &mut Vec<FlatJsonValue<V>>) -> Value<V> {
     _serialize_to_json(data, 1)
 }
 
-pub fn serialize_to_json_with_option<'a, V: Debug + Clone + AsRef<str> + GetBytes>(data: &mut Vec<FlatJsonValue<V>>, root_depth: u8) -> Value<V> {
+pub fn serialize_to_json_with_option<V: Debug + Clone + AsRef<str> + GetBytes>(data: &mut Vec<FlatJsonValue<V>>, root_depth: u8) -> Value<V> {
     _serialize_to_json(data, root_depth)
 }
 
-pub fn _serialize_to_json<'a, V: Debug + Clone + AsRef<str> + GetBytes>(data: &mut Vec<FlatJsonValue<V>>, root_depth: u8) -> Value<V> {
+/// Like `serialize_to_json_with_option`, emitting members and elements in `order`. Other serialize functions use `Order::Document`.
+pub fn serialize_to_json_ordered<V: Debug + Clone + AsRef<str> + GetBytes>(data: &mut Vec<FlatJsonValue<V>>, root_depth: u8, order: Order) -> Value<V> {
+    if order == Order::Entries {
+        // entries are consumed by the serialization, their positions can be overwritten
+        data.iter_mut().enumerate().for_each(|(i, entry)| entry.pointer.position = i);
+    }
+    _serialize_to_json(data, root_depth)
+}
+
+pub fn _serialize_to_json<V: Debug + Clone + AsRef<str> + GetBytes>(data: &mut Vec<FlatJsonValue<V>>, root_depth: u8) -> Value<V> {
     let mut root = Value::Object(new_map::<V>());
     let mut root_array = Value::Array(Vec::with_capacity(128));
 
@@ -71,12 +91,19 @@ pub fn _serialize_to_json<'a, V: Debug + Clone + AsRef<str> + GetBytes>(data: &m
         let entry = sorted_data.pop().unwrap();
         let key = entry.pointer;
         let value = entry.value;
+        if matches!(key.value_type, ValueType::None) {
+            continue;
+        }
 
         if key.pointer.is_empty() && matches!(key.value_type, ValueType::Array(_)) {
             root_is_obj = false;
             current_parent = &mut root_array;
             continue;
         }
+        if key.pointer.is_empty() && matches!(key.value_type, ValueType::Object(_)) {
+            // root object entry, e.g. with `envelope_only`, members are added to the root
+            continue;
+        }
 
         if key.depth == root_depth {
             match current_parent {
@@ -87,7 +114,12 @@ pub fn _serialize_to_json<'a, V: Debug + Clone + AsRef<str> + GetBytes>(data: &m
                         key.pointer.splitn(root_depth as usize + 1, '/').last().unwrap().to_owned()
                     };
                     match key.value_type {
-                        ValueType::Object(_) => { obj.insert(pointer.to_owned(), Value::Object(new_map())); }
+                        ValueType::Object(parsed) => {
+                            match value {
+                                Some(value) if !parsed => { obj.insert(pointer.to_owned(), Value::ObjectSerialized(value)); }
+                                _ => { obj.insert(pointer.to_owned(), Value::Object(new_map())); }
+                            }
+                        }
                         ValueType::Array(len) => {
                             if let Some(value) = value {
                                 obj.insert(pointer.to_owned(), Value::ArraySerialized(value));
@@ -100,7 +132,12 @@ pub fn _serialize_to_json<'a, V: Debug + Clone + AsRef<str> + GetBytes>(data: &m
                 }
                 Value::Array(array) => {
                     match key.value_type {
-                        ValueType::Object(_) => { array.push(Value::Object(new_map())); }
+                        ValueType::Object(parsed) => {
+                            match value {
+                                Some(value) if !parsed => { array.push(Value::ObjectSerialized(value)); }
+                                _ => { array.push(Value::Object(new_map())); }
+                            }
+                        }
                         ValueType::Array(len) => {
                             if let Some(value) = value {
                                 array.push(Value::ArraySerialized(value));
@@ -115,7 +152,7 @@ pub fn _serialize_to_json<'a, V: Debug + Clone + AsRef<str> + GetBytes>(data: &m
             }
         } else if key.depth > root_depth {
             let split = key.pointer.split('/');
-            let key_pointer_iter = split.filter(|s| !s.is_empty());
+            let mut key_pointer_iter = split.filter(|s| !s.is_empty());
             let start: usize = root_depth as usize - 1;
             let key_pointer_len = key_pointer_iter.clone().count();
 
@@ -154,11 +191,11 @@ pub fn _serialize_to_json<'a, V: Debug + Clone + AsRef<str> + GetBytes>(data: &m
                         previous_parent_pointer.push(s.to_owned());
                     }
                 }
-                let b = &previous_parent_pointer[0].as_bytes()[0];
-                if *b >= 0x30 && *b <= 0x39 {
-                    current_parent = &mut root_array;
-                } else {
+                // the root array entry, when any, has the lowest depth so it has been seen already: a first segment looking like an index can be an object key
+                if root_is_obj {
                     current_parent = &mut root;
+                } else {
+                    current_parent = &mut root_array;
                 }
                 for (i, s) in key_pointer_iter.clone().enumerate() {
                     if i < start {
@@ -186,11 +223,16 @@ pub fn _serialize_to_json<'a, V: Debug + Clone + AsRef<str> + GetBytes>(data: &m
                 }
             }
 
-            let k = key_pointer_iter.last().unwrap();
+            let k = key_pointer_iter.next_back().unwrap();
             match current_parent {
                 Value::Object(obj) => {
                     match key.value_type {
-                        ValueType::Object(_) => { obj.insert(k.to_owned(), Value::Object(new_map())); }
+                        ValueType::Object(parsed) => {
+                            match value {
+                                Some(value) if !parsed => { obj.insert(k.to_owned(), Value::ObjectSerialized(value)); }
+                                _ => { obj.insert(k.to_owned(), Value::Object(new_map())); }
+                            }
+                        }
                         ValueType::Array(len) => {
                             if let Some(value) = value {
                                 obj.insert(k.to_owned(), Value::ArraySerialized(value));
@@ -203,7 +245,12 @@ pub fn _serialize_to_json<'a, V: Debug + Clone + AsRef<str> + GetBytes>(data: &m
                 }
                 Value::Array(array) => {
                     match key.value_type {
-                        ValueType::Object(_) => { array.push(Value::Object(new_map())); }
+                        ValueType::Object(parsed) => {
+                            match value {
+                                Some(value) if !parsed => { array.push(Value::ObjectSerialized(value)); }
+                                _ => { array.push(Value::Object(new_map())); }
+                            }
+                        }
                         ValueType::Array(len) => {
                             if let Some(value) = value {
                                 array.push(Value::ArraySerialized(value));
@@ -251,38 +298,118 @@ fn value_to_json<V: Debug + Clone + AsRef<str> + GetBytes>(value: Option<V>, val
     }
 }
 
+// values are kept as written, so quotes already escaped, i.e. preceded by an odd number of backslashes, are left untouched
+fn escape_quotes(value: &str) -> Cow<'_, str> {
+    if !value.contains('"') {
+        return Cow::Borrowed(value);
+    }
+    let mut escaped = String::with_capacity(value.len() + 2);
+    let mut backslashes = 0;
+    for c in value.chars() {
+        if c == '"' && backslashes % 2 == 0 {
+            escaped.push('\\');
+        }
+        backslashes = if c == '\\' { backslashes + 1 } else { 0 };
+        escaped.push(c);
+    }
+    Cow::Owned(escaped)
+}
+
+/// Indentation of one nesting level, see [`SerializeOptions`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indent {
+    Spaces(usize),
+    Tabs,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Newline {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+impl Newline {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Newline::Lf => "\n",
+            Newline::CrLf => "\r\n",
+        }
+    }
+}
+
+/// Layout of [`Value::to_json_with_options`], e.g. to match the style of a user-authored file so diffs stay clean after rewriting it.
+/// Defaults to the layout of `to_json`: two spaces, `\n` and a space after colons. Arrays of scalars are always written on one line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SerializeOptions {
+    pub indent: Indent,
+    pub newline: Newline,
+    pub space_after_colon: bool,
+}
+
+impl Default for SerializeOptions {
+    fn default() -> Self {
+        Self { indent: Indent::Spaces(2), newline: Newline::Lf, space_after_colon: true }
+    }
+}
+
+impl SerializeOptions {
+    pub fn indent(mut self, indent: Indent) -> Self {
+        self.indent = indent;
+        self
+    }
+    pub fn newline(mut self, newline: Newline) -> Self {
+        self.newline = newline;
+        self
+    }
+    pub fn space_after_colon(mut self, space_after_colon: bool) -> Self {
+        self.space_after_colon = space_after_colon;
+        self
+    }
+    fn indentation(&self, depth: usize) -> String {
+        match self.indent {
+            Indent::Spaces(n) => " ".repeat(n * depth),
+            Indent::Tabs => "\t".repeat(depth),
+        }
+    }
+}
+
 impl<V: ToString + AsRef<str>> Value<V> {
     pub fn to_json(&self) -> String {
-        self._to_json(1)
+        self.to_json_with_options(&SerializeOptions::default())
+    }
+    pub fn to_json_with_options(&self, options: &SerializeOptions) -> String {
+        self._to_json(1, options)
     }
-    fn _to_json(&self, depth: usize) -> String {
+    fn _to_json(&self, depth: usize, options: &SerializeOptions) -> String {
+        let newline = options.newline.as_str();
         match self {
             Value::Object(obj) => {
-                let members: Vec<String> = obj.iter().map(|(k, v)| format!("{:indent$}\"{}\": {}", "", k, v._to_json(depth + 1), indent = depth * 2)).collect();
-                format!("{{\n{}\n{:indent$}}}", members.join(",\n"), "", indent = (depth - 1) * 2)
+                let colon = if options.space_after_colon { ": " } else { ":" };
+                let members: Vec<String> = obj.iter().map(|(k, v)| format!("{}\"{}\"{}{}", options.indentation(depth), k, colon, v._to_json(depth + 1, options))).collect();
+                format!("{{{}{}{}{}}}", newline, members.join(&format!(",{}", newline)), newline, options.indentation(depth - 1))
             }
             Value::Array(arr) => {
                 let mut contains_nested_array = false;
                 let elements: Vec<String> = arr.iter().map(|v| {
                     if matches!(v, Value::Array(_)) || matches!(v, Value::Object(_)) {
                         contains_nested_array = true;
-                        format!("{:indent$}{}", "", v._to_json(depth + 1), indent = (depth) * 2)
+                        format!("{}{}", options.indentation(depth), v._to_json(depth + 1, options))
                     } else {
-                        v._to_json(depth)
+                        v._to_json(depth, options)
                     }
                 }).collect();
                 if contains_nested_array {
-                    format!("[\n{}\n{:indent$}]", elements.join(",\n"), "", indent = (depth - 1) * 2)
+                    format!("[{}{}{}{}]", newline, elements.join(&format!(",{}", newline)), newline, options.indentation(depth - 1))
                 } else {
                     format!("[{}]", elements.join(", "))
                 }
             }
             Value::Number(num) => num.to_string(),
-            Value::String(s) => format!("\"{}\"", s.as_ref().replace('\"', "\\\"")),
+            Value::String(s) => format!("\"{}\"", escape_quotes(s.as_ref())),
             Value::Bool(b) => b.to_string(),
             Value::Null => "null".to_string(),
-            Value::ArraySerialized(value) => value.to_string(),
-            _ => panic!("todo")
+            Value::ArraySerialized(value) | Value::ObjectSerialized(value) => value.to_string(),
         }
     }
 }
@@ -291,7 +418,7 @@ impl<V: ToString + AsRef<str>> Value<V> {
 #[cfg(feature = "indexmap")] // to ease testing we use indexmap to have deterministic output
 mod tests {
     use crate::{FlatJsonValue, JSONParser, ParseOptions, PointerKey, ValueType};
-    use crate::serializer::{serialize_to_json, serialize_to_json_with_option};
+    use crate::serializer::{serialize_to_json, serialize_to_json_ordered, serialize_to_json_with_option, Indent, Newline, Order, SerializeOptions};
     
 
     #[test]
@@ -316,6 +443,46 @@ mod tests {
         let value = serialize_to_json(&mut vec);
         assert_eq!(value.to_json(), json);
     }
+    #[test]
+    fn serialize_options() {
+        let json = r#"{"id": 1, "flags": {"a": true, "b": [1, 2]}, "items": [{"c": null}]}"#;
+        let value = serialize_to_json(&mut JSONParser::parse(json, ParseOptions::default()).unwrap().json);
+        assert_eq!(value.to_json_with_options(&SerializeOptions::default()), value.to_json());
+        assert_eq!(value.to_json_with_options(&SerializeOptions::default().indent(Indent::Tabs).newline(Newline::CrLf).space_after_colon(false)),
+                   "{\r\n\t\"id\":1,\r\n\t\"flags\":{\r\n\t\t\"a\":true,\r\n\t\t\"b\":[1, 2]\r\n\t},\r\n\t\"items\":[\r\n\t\t{\r\n\t\t\t\"c\":null\r\n\t\t}\r\n\t]\r\n}");
+        assert_eq!(value.to_json_with_options(&SerializeOptions::default().indent(Indent::Spaces(4))),
+                   "{\n    \"id\": 1,\n    \"flags\": {\n        \"a\": true,\n        \"b\": [1, 2]\n    },\n    \"items\": [\n        {\n            \"c\": null\n        }\n    ]\n}");
+    }
+
+    #[test]
+    fn serialize_in_order() {
+        let json =
+            r#"{
+  "name": "NV_BASIC",
+  "id": 1,
+  "flags": {
+    "z": true,
+    "a": [2, 1]
+  },
+  "bbb": null
+}"#;
+
+        let mut vec = JSONParser::parse(json, ParseOptions::default()).unwrap().json;
+        vec.sort_by(|a, b| a.pointer.pointer.cmp(&b.pointer.pointer));
+        let mut sorted = vec.clone();
+        assert_eq!(serialize_to_json_ordered(&mut vec, 1, Order::Document).to_json(), json);
+        assert_eq!(serialize_to_json_ordered(&mut sorted, 1, Order::Entries).to_json(),
+                   r#"{
+  "bbb": null,
+  "flags": {
+    "a": [2, 1],
+    "z": true
+  },
+  "id": 1,
+  "name": "NV_BASIC"
+}"#);
+    }
+
     #[test]
     fn missing_parent() {
         let json =
@@ -346,6 +513,11 @@ mod tests {
                 depth: 2,
                 index: 0,
                 position: 0,
+                string_subtype: None,
+                key_span: None,
+                original_len: None,
+                role: None,
+                key_count: None,
             },
             value: Some("true"),
         });
@@ -648,6 +820,11 @@ mod tests {
             depth: 0,
             index: 0,
             position: 0,
+            string_subtype: None,
+            key_span: None,
+            original_len: None,
+            role: None,
+            key_count: None,
         }, value: None });
         let value = serialize_to_json_with_option(&mut vec, 4);
         assert_eq!(value.to_json().replace(' ', ""), json_depth_2[16].value.unwrap().replace(' ', ""));
@@ -662,6 +839,11 @@ mod tests {
             depth: 0,
             index: 0,
             position: 0,
+            string_subtype: None,
+            key_span: None,
+            original_len: None,
+            role: None,
+            key_count: None,
         }, value: None });
         let value = serialize_to_json_with_option(&mut vec, 5);
         assert_eq!(value.to_json().replace(' ', ""), json_depth_2[15].value.unwrap().replace(' ', ""));
@@ -1105,4 +1287,156 @@ mod tests {
         let value = serialize_to_json(&mut vec);
         assert_eq!(value.to_json(), json);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn envelope_only() {
+        let json =
+            r#"{
+  "status": "ok",
+  "data": [{"id": 1}, {"id": 2}]
+}"#;
+
+        let mut vec = JSONParser::parse(json, ParseOptions::default().envelope_only(true)).unwrap().json;
+        assert_eq!(vec[0].pointer.pointer, "");
+        let value = serialize_to_json(&mut vec);
+        assert_eq!(value.to_json(), json);
+    }
+
+    #[test]
+    fn escaped_quote_and_raw_object() {
+        let json =
+            r#"{
+  "a": "\"b\\",
+  "c": {"d": "}"}
+}"#;
+
+        let mut vec = JSONParser::parse(json, ParseOptions::default().max_depth(1)).unwrap().json;
+        let value = serialize_to_json(&mut vec);
+        assert_eq!(value.to_json(), json);
+    }
+
+    #[test]
+    fn numeric_keys() {
+        let json =
+            r#"{
+  "0": "a",
+  "10": {
+    "1": [1, 2],
+    "2": {
+      "0": true
+    }
+  }
+}"#;
+
+        let res = JSONParser::parse(json, ParseOptions::default()).unwrap();
+        let pointers: Vec<&str> = res.json.iter().map(|e| e.pointer.pointer.as_str()).collect();
+        assert_eq!(pointers, vec!["/0", "/10", "/10/1", "/10/1/0", "/10/1/1", "/10/2", "/10/2/0"]);
+        assert_eq!(res.json[1].pointer.value_type, ValueType::Object(true));
+        let mut vec = res.json;
+        let value = serialize_to_json(&mut vec);
+        assert_eq!(value.to_json(), json);
+
+        let json = r#"[{"0": "a"}, {"1": {"2": null}}]"#;
+        let mut vec = JSONParser::parse(json, ParseOptions::default()).unwrap().json;
+        let value = serialize_to_json(&mut vec);
+        assert_eq!(value.to_json().replace([' ', '\n'], ""), json.replace(' ', ""));
+    }
+
+    #[test]
+    fn none_is_absent_null_is_null() {
+        let json =
+            r#"{
+  "id": 1,
+  "bbb": null
+}"#;
+
+        let mut vec = JSONParser::parse(json, ParseOptions::default()).unwrap().json;
+        vec.push(FlatJsonValue { pointer: PointerKey::from_pointer("/absent".to_string(), ValueType::None, 1, 3), value: None });
+        vec.push(FlatJsonValue { pointer: PointerKey::from_pointer("/absent_with_value".to_string(), ValueType::None, 1, 4), value: Some("1") });
+        vec.push(FlatJsonValue { pointer: PointerKey::from_pointer("/null".to_string(), ValueType::Null, 1, 5), value: None });
+        let value = serialize_to_json(&mut vec);
+        assert_eq!(value.to_json(), r#"{
+  "id": 1,
+  "bbb": null,
+  "null": null
+}"#);
+    }
+}
+
+// parse -> serialize -> parse round trip on generated documents, proptest shrinks failing documents and stores their seed in proptest-regressions/ to replay them first
+#[cfg(test)]
+mod round_trip_tests {
+    use proptest::prelude::*;
+
+    use crate::serializer::serialize_to_json;
+    use crate::{JSONParser, ParseOptions, ParseResult, ValueType};
+
+    fn number() -> impl Strategy<Value=String> {
+        prop_oneof![
+            any::<i64>().prop_map(|n| n.to_string()),
+            (any::<i32>(), 0..1_000_000_u32).prop_map(|(integer, fraction)| format!("{}.{}", integer, fraction)),
+            Just("-0".to_string()),
+            Just("0.1".to_string()),
+            Just("12345678901234567890".to_string()),
+            Just("-0.000123".to_string()),
+        ]
+    }
+
+    fn string() -> impl Strategy<Value=String> {
+        let fragment = prop_oneof![
+            "[^\"\\\\\\x00-\\x1f]",
+            Just("\\\"".to_string()),
+            Just("\\\\".to_string()),
+            Just("\\n".to_string()),
+            Just("\\u00e9".to_string()),
+        ];
+        prop::collection::vec(fragment, 0..12).prop_map(|fragments| format!("\"{}\"", fragments.concat()))
+    }
+
+    // '/' and '~' are not escaped in pointers
+    fn key() -> impl Strategy<Value=String> {
+        "[a-zA-Z0-9_ é]{1,8}"
+    }
+
+    fn value() -> impl Strategy<Value=String> {
+        let leaf = prop_oneof![Just("null".to_string()), Just("true".to_string()), Just("false".to_string()), number(), string()];
+        leaf.prop_recursive(8, 128, 20, |inner| prop_oneof![
+            prop::collection::vec(inner.clone(), 0..8).prop_map(|elements| format!("[{}]", elements.join(", "))),
+            prop::collection::hash_map(key(), inner, 0..20).prop_map(|members| {
+                let members: Vec<String> = members.into_iter().map(|(k, v)| format!("\"{}\": {}", k, v)).collect();
+                format!("{{{}}}", members.join(", "))
+            }),
+        ])
+    }
+
+    fn document() -> impl Strategy<Value=String> {
+        value().prop_filter("a document is an object or an array", |v| v.starts_with('{') || v.starts_with('['))
+    }
+
+    // raw data of containers depends on formatting, only their pointer and type are compared
+    fn fully_parsed(json: &str) -> ParseResult<&str> {
+        let mut res = JSONParser::parse(json, ParseOptions::default().max_depth(64)).unwrap();
+        for entry in res.json.iter_mut().filter(|entry| entry.pointer.value_type.is_container()) {
+            entry.value = None;
+        }
+        res
+    }
+
+    proptest! {
+        #[test]
+        fn parse_serialize_parse(json in document(), max_depth in prop_oneof![Just(1_u8), Just(3), Just(10)]) {
+            let mut res = JSONParser::parse(&json, ParseOptions::default().max_depth(max_depth)).unwrap();
+            let serialized = serialize_to_json(&mut res.json).to_json();
+            let expected = fully_parsed(&json);
+            let actual = fully_parsed(&serialized);
+            prop_assert!(expected.structurally_equal(&actual, true), "{}\nserialized as\n{}", json, serialized);
+        }
+
+        #[test]
+        fn root_array_length(elements in prop::collection::vec(number(), 1..50)) {
+            let json = format!("[{}]", elements.join(","));
+            let res = JSONParser::parse(&json, ParseOptions::default()).unwrap();
+            prop_assert_eq!(res.json[0].pointer.value_type, ValueType::Array(elements.len()));
+        }
+    }
+}
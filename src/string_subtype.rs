@@ -0,0 +1,108 @@
+/// Refinement of a `String` value detected with `ParseOptions::detect_string_subtypes`.
+/// Detection is conservative: values containing escape sequences are never classified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StringSubtype {
+    /// `8-4-4-4-12` hexadecimal digits separated by `-`, e.g. `123e4567-e89b-12d3-a456-426614174000`.
+    Uuid,
+    /// `local@domain.tld`: a single `@`, local part made of alphanumerics and `.` `_` `%` `+` `-` without leading, trailing or consecutive `.`,
+    /// domain made of at least two alphanumerics and `-` labels separated by `.`, the last one being at least 2 letters.
+    Email,
+    /// `http://` or `https://` followed by a host made of alphanumerics, `.` and `-`, an optional port, path, query or fragment, and no whitespace.
+    Url,
+}
+
+impl StringSubtype {
+    /// Subtype of the raw `value` of a string, `None` when it matches no pattern.
+    pub fn detect(value: &str) -> Option<StringSubtype> {
+        if value.contains('\\') {
+            return None;
+        }
+        if is_uuid(value) {
+            Some(StringSubtype::Uuid)
+        } else if is_email(value) {
+            Some(StringSubtype::Email)
+        } else if is_url(value) {
+            Some(StringSubtype::Url)
+        } else {
+            None
+        }
+    }
+}
+
+fn is_uuid(value: &str) -> bool {
+    let bytes = value.as_bytes();
+    bytes.len() == 36 && bytes.iter().enumerate().all(|(i, b)| match i {
+        8 | 13 | 18 | 23 => *b == b'-',
+        _ => b.is_ascii_hexdigit(),
+    })
+}
+
+fn is_email(value: &str) -> bool {
+    let Some((local, domain)) = value.split_once('@') else {
+        return false;
+    };
+    let local_is_valid = !local.is_empty() && !local.starts_with('.') && !local.ends_with('.') && !local.contains("..")
+        && local.bytes().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'.' | b'_' | b'%' | b'+' | b'-'));
+    local_is_valid && is_domain(domain)
+}
+
+fn is_domain(domain: &str) -> bool {
+    let labels: Vec<&str> = domain.split('.').collect();
+    let tld = labels[labels.len() - 1];
+    labels.len() >= 2
+        && labels.iter().all(|label| !label.is_empty() && !label.starts_with('-') && !label.ends_with('-') && label.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-'))
+        && tld.len() >= 2 && tld.bytes().all(|b| b.is_ascii_alphabetic())
+}
+
+fn is_url(value: &str) -> bool {
+    let Some(rest) = value.strip_prefix("https://").or_else(|| value.strip_prefix("http://")) else {
+        return false;
+    };
+    let authority_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let (host, port) = match rest[0..authority_end].split_once(':') {
+        Some((host, port)) => (host, Some(port)),
+        None => (&rest[0..authority_end], None),
+    };
+    !host.is_empty() && !host.starts_with('.') && !host.ends_with('.') && host.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'.' || b == b'-')
+        && port.is_none_or(|port| !port.is_empty() && port.len() <= 5 && port.bytes().all(|b| b.is_ascii_digit()))
+        && !rest.bytes().any(|b| b.is_ascii_whitespace() || b.is_ascii_control())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::string_subtype::StringSubtype;
+    use crate::{JSONParser, ParseOptions};
+
+    #[test]
+    fn detect() {
+        assert_eq!(StringSubtype::detect("123e4567-e89b-12d3-a456-426614174000"), Some(StringSubtype::Uuid));
+        assert_eq!(StringSubtype::detect("123e4567-e89b-12d3-a456-42661417400g"), None);
+        assert_eq!(StringSubtype::detect("123e4567e89b12d3a456426614174000"), None);
+        assert_eq!(StringSubtype::detect("john.doe+tag@mail.example.com"), Some(StringSubtype::Email));
+        assert_eq!(StringSubtype::detect("john@localhost"), None);
+        assert_eq!(StringSubtype::detect("john..doe@example.com"), None);
+        assert_eq!(StringSubtype::detect("a@b@example.com"), None);
+        assert_eq!(StringSubtype::detect("@example.com"), None);
+        assert_eq!(StringSubtype::detect("john@example.c0m"), None);
+        assert_eq!(StringSubtype::detect("https://example.com"), Some(StringSubtype::Url));
+        assert_eq!(StringSubtype::detect("http://api.example.com:8080/v1/items?id=1#top"), Some(StringSubtype::Url));
+        assert_eq!(StringSubtype::detect("https://"), None);
+        assert_eq!(StringSubtype::detect("https://exa mple.com"), None);
+        assert_eq!(StringSubtype::detect("https://example.com:port"), None);
+        assert_eq!(StringSubtype::detect("ftp://example.com"), None);
+        assert_eq!(StringSubtype::detect("https:\\/\\/example.com"), None);
+        assert_eq!(StringSubtype::detect("hello"), None);
+    }
+
+    #[test]
+    fn detect_string_subtypes_option() {
+        let json = r#"{"id": "123e4567-e89b-12d3-a456-426614174000", "contact": {"email": "a@b.io"}, "site": "https://b.io", "name": "Bash", "n": 1}"#;
+        let res = JSONParser::parse(json, ParseOptions::default()).unwrap();
+        assert!(res.json.iter().all(|e| e.pointer.string_subtype.is_none()));
+        let res = JSONParser::parse(json, ParseOptions::default().detect_string_subtypes(true)).unwrap();
+        let subtypes: Vec<(&str, Option<StringSubtype>)> = res.json.iter().map(|e| (e.pointer.pointer.as_str(), e.pointer.string_subtype)).collect();
+        assert_eq!(subtypes, vec![("/id", Some(StringSubtype::Uuid)), ("/contact", None), ("/contact/email", Some(StringSubtype::Email)),
+                                  ("/site", Some(StringSubtype::Url)), ("/name", None), ("/n", None)]);
+        assert_eq!(res.json[2].value, Some("a@b.io"));
+    }
+}
@@ -0,0 +1,246 @@
+use std::borrow::Cow;
+use std::fmt::Debug;
+
+use crate::events::JsonEvent;
+use crate::{GetBytes, JSONParser, ParseOptions, ParseResult, ValueType};
+
+/// Owned tree of a document, see [`ParseResult::to_tree`]. Keys and values are as written in the input, they are not unescaped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JsonNode {
+    /// Members in document order, duplicated keys are kept.
+    Object(Vec<(String, JsonNode)>),
+    Array(Vec<JsonNode>),
+    Number(String),
+    String(String),
+    Bool(bool),
+    Null,
+}
+
+impl JsonNode {
+    /// Member of an object, the first one when the key is duplicated.
+    pub fn get(&self, key: &str) -> Option<&JsonNode> {
+        match self {
+            JsonNode::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, node)| node),
+            _ => None,
+        }
+    }
+
+    pub fn get_index(&self, index: usize) -> Option<&JsonNode> {
+        match self {
+            JsonNode::Array(elements) => elements.get(index),
+            _ => None,
+        }
+    }
+}
+
+/// Tree of a document borrowing its text from the parse result, see [`ParseResult::as_json_value_borrowed`]. Keys are borrowed from pointers, they are owned only for containers expanded from raw data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BorrowedValue<'a> {
+    /// Members in document order, duplicated keys are kept.
+    Object(Vec<(Cow<'a, str>, BorrowedValue<'a>)>),
+    Array(Vec<BorrowedValue<'a>>),
+    Number(&'a str),
+    String(&'a str),
+    Bool(bool),
+    Null,
+}
+
+impl<'a> BorrowedValue<'a> {
+    /// Member of an object, the first one when the key is duplicated.
+    pub fn get(&self, key: &str) -> Option<&BorrowedValue<'a>> {
+        match self {
+            BorrowedValue::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn get_index(&self, index: usize) -> Option<&BorrowedValue<'a>> {
+        match self {
+            BorrowedValue::Array(elements) => elements.get(index),
+            _ => None,
+        }
+    }
+
+    // same tree with values borrowed from `raw`, values of this tree being slices of it
+    fn rebase<'b>(self, raw: &'b str) -> BorrowedValue<'b> {
+        let rebase_str = |value: &str| {
+            let start = value.as_ptr() as usize - raw.as_ptr() as usize;
+            &raw[start..start + value.len()]
+        };
+        match self {
+            BorrowedValue::Object(members) => BorrowedValue::Object(members.into_iter().map(|(key, value)| (Cow::Owned(key.into_owned()), value.rebase(raw))).collect()),
+            BorrowedValue::Array(elements) => BorrowedValue::Array(elements.into_iter().map(|value| value.rebase(raw)).collect()),
+            BorrowedValue::Number(value) => BorrowedValue::Number(rebase_str(value)),
+            BorrowedValue::String(value) => BorrowedValue::String(rebase_str(value)),
+            BorrowedValue::Bool(value) => BorrowedValue::Bool(value),
+            BorrowedValue::Null => BorrowedValue::Null,
+        }
+    }
+}
+
+impl<V: Debug + Clone + AsRef<str> + GetBytes> ParseResult<V> {
+    /// Nested tree reconstructed from flat entries, a dependency free alternative to a serde conversion. Order of object members and array elements is the document one.
+    /// Containers beyond `max_depth` are built by parsing their raw data, they are empty when raw data was not kept and `Null` when it can't be parsed.
+    pub fn to_tree(&self) -> JsonNode {
+        // containers being built, along with the key they are a member of
+        let mut stack: Vec<(Option<String>, JsonNode)> = Vec::new();
+        let mut key: Option<String> = None;
+        for event in self.as_events() {
+            let node = match event {
+                JsonEvent::StartObject => {
+                    stack.push((key.take(), JsonNode::Object(vec![])));
+                    continue;
+                }
+                JsonEvent::StartArray => {
+                    stack.push((key.take(), JsonNode::Array(vec![])));
+                    continue;
+                }
+                JsonEvent::Key(k) => {
+                    key = Some(k.to_string());
+                    continue;
+                }
+                JsonEvent::EndObject | JsonEvent::EndArray => {
+                    let (container_key, node) = stack.pop().unwrap();
+                    key = container_key;
+                    node
+                }
+                JsonEvent::Value(value_type, value) => leaf(value_type, value),
+            };
+            match stack.last_mut() {
+                Some((_, JsonNode::Object(members))) => members.push((key.take().unwrap_or_default(), node)),
+                Some((_, JsonNode::Array(elements))) => elements.push(node),
+                _ => return node,
+            }
+        }
+        JsonNode::Null
+    }
+
+    /// Like `to_tree`, without copying keys, strings and numbers which are borrowed from this result: a navigable tree for performance sensitive consumers.
+    pub fn as_json_value_borrowed(&self) -> BorrowedValue<'_> {
+        let mut stack: Vec<(Option<&str>, BorrowedValue)> = Vec::new();
+        let mut key: Option<&str> = None;
+        for event in self.as_events() {
+            let value = match event {
+                JsonEvent::StartObject => {
+                    stack.push((key.take(), BorrowedValue::Object(vec![])));
+                    continue;
+                }
+                JsonEvent::StartArray => {
+                    stack.push((key.take(), BorrowedValue::Array(vec![])));
+                    continue;
+                }
+                JsonEvent::Key(k) => {
+                    key = Some(k);
+                    continue;
+                }
+                JsonEvent::EndObject | JsonEvent::EndArray => {
+                    let (container_key, value) = stack.pop().unwrap();
+                    key = container_key;
+                    value
+                }
+                JsonEvent::Value(value_type, value) => borrowed_leaf(value_type, value),
+            };
+            match stack.last_mut() {
+                Some((_, BorrowedValue::Object(members))) => members.push((Cow::Borrowed(key.take().unwrap_or_default()), value)),
+                Some((_, BorrowedValue::Array(elements))) => elements.push(value),
+                _ => return value,
+            }
+        }
+        BorrowedValue::Null
+    }
+}
+
+fn borrowed_leaf(value_type: ValueType, value: Option<&str>) -> BorrowedValue<'_> {
+    match (value_type, value) {
+        (ValueType::Object(_), None) => BorrowedValue::Object(vec![]),
+        (ValueType::Array(_), None) => BorrowedValue::Array(vec![]),
+        (ValueType::Object(_) | ValueType::Array(_), Some(raw)) => JSONParser::parse(raw, ParseOptions::default().max_depth(u8::MAX))
+            .map(|res| res.as_json_value_borrowed().rebase(raw))
+            .unwrap_or(BorrowedValue::Null),
+        (ValueType::String, Some(value)) => BorrowedValue::String(value),
+        (ValueType::Number, Some(value)) => BorrowedValue::Number(value),
+        (ValueType::Bool, Some(value)) => BorrowedValue::Bool(value == "true"),
+        _ => BorrowedValue::Null,
+    }
+}
+
+fn leaf(value_type: ValueType, value: Option<&str>) -> JsonNode {
+    match (value_type, value) {
+        (ValueType::Object(_), None) => JsonNode::Object(vec![]),
+        (ValueType::Array(_), None) => JsonNode::Array(vec![]),
+        (ValueType::Object(_) | ValueType::Array(_), Some(raw)) => JSONParser::parse(raw, ParseOptions::default().max_depth(u8::MAX))
+            .map(|res| res.to_tree())
+            .unwrap_or(JsonNode::Null),
+        (ValueType::String, Some(value)) => JsonNode::String(value.to_string()),
+        (ValueType::Number, Some(value)) => JsonNode::Number(value.to_string()),
+        (ValueType::Bool, Some(value)) => JsonNode::Bool(value == "true"),
+        _ => JsonNode::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "indexmap")]
+    use crate::serializer::serialize_to_json;
+    use crate::tree::{BorrowedValue, JsonNode};
+    use crate::{JSONParser, ParseOptions};
+
+    fn string(value: &str) -> JsonNode {
+        JsonNode::String(value.to_string())
+    }
+
+    fn number(value: &str) -> JsonNode {
+        JsonNode::Number(value.to_string())
+    }
+
+    #[test]
+    fn to_tree() {
+        let json = r#"{"b": 1, "a": [true, {"c": null}, "x"], "d": {}, "e": []}"#;
+        let res = JSONParser::parse(json, ParseOptions::default()).unwrap();
+        let tree = res.to_tree();
+        assert_eq!(tree, JsonNode::Object(vec![
+            ("b".to_string(), number("1")),
+            ("a".to_string(), JsonNode::Array(vec![JsonNode::Bool(true), JsonNode::Object(vec![("c".to_string(), JsonNode::Null)]), string("x")])),
+            ("d".to_string(), JsonNode::Object(vec![])),
+            ("e".to_string(), JsonNode::Array(vec![])),
+        ]));
+        assert_eq!(tree.get("a").and_then(|a| a.get_index(2)), Some(&string("x")));
+
+        let res = JSONParser::parse(r#"[[1, 2], {"a": "b"}]"#, ParseOptions::default()).unwrap();
+        assert_eq!(res.to_tree(), JsonNode::Array(vec![JsonNode::Array(vec![number("1"), number("2")]), JsonNode::Object(vec![("a".to_string(), string("b"))])]));
+    }
+
+    #[test]
+    fn to_tree_round_trip() {
+        let json = r#"{"id": 1, "skills": [{"name": "a", "levels": [1, 2]}, {"name": "b", "levels": []}], "meta": {"deep": {"deeper": [null]}}}"#;
+        let tree = JSONParser::parse(json, ParseOptions::default()).unwrap().to_tree();
+        #[cfg(feature = "indexmap")] { // serializer keeps key order only with indexmap
+            let mut entries = JSONParser::parse(json, ParseOptions::default()).unwrap().json;
+            let serialized = serialize_to_json(&mut entries).to_json();
+            assert_eq!(JSONParser::parse(&serialized, ParseOptions::default()).unwrap().to_tree(), tree);
+        }
+        // containers beyond max_depth are built from their raw data
+        assert_eq!(JSONParser::parse(json, ParseOptions::default().max_depth(1)).unwrap().to_tree(), tree);
+    }
+
+    #[test]
+    fn as_json_value_borrowed() {
+        let json = r#"{"b": 1, "a": [true, {"c": null}, "x\"y"], "d": {"e": {"f": [2.5]}}}"#;
+        let res = JSONParser::parse(json, ParseOptions::default().max_depth(2)).unwrap();
+        let value = res.as_json_value_borrowed();
+        assert_eq!(value.get("b"), Some(&BorrowedValue::Number("1")));
+        assert_eq!(value.get("a").and_then(|a| a.get_index(2)), Some(&BorrowedValue::String(r#"x\"y"#)));
+        assert_eq!(value.get("a").and_then(|a| a.get_index(1)).and_then(|o| o.get("c")), Some(&BorrowedValue::Null));
+        // expanded from the raw data of /d/e, text still borrowed from the result
+        let BorrowedValue::Number(number) = value.get("d").and_then(|d| d.get("e")).and_then(|e| e.get("f")).and_then(|f| f.get_index(0)).unwrap() else {
+            panic!("not a number");
+        };
+        assert_eq!(*number, "2.5");
+        let raw = res.json.iter().find(|e| e.pointer.pointer == "/d/e").unwrap().value.unwrap();
+        assert!(raw.as_bytes().as_ptr_range().contains(&number.as_ptr()));
+        let BorrowedValue::Object(members) = &value else {
+            panic!("not an object");
+        };
+        assert!(members.iter().all(|(key, _)| matches!(key, std::borrow::Cow::Borrowed(_))));
+    }
+}
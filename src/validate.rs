@@ -0,0 +1,380 @@
+use std::io::{ErrorKind, Read};
+
+use crate::error::ParseError;
+use crate::{JSONParser, ParseOptions};
+
+const BUFFER_LEN: usize = 8 * 1024;
+
+impl JSONParser {
+    /// Fail-fast check that `reader` holds one valid JSON document, e.g. before accepting an upload, returning the error along with the byte offset where it was detected.
+    /// The grammar is the strict one of RFC 8259, numbers with an exponent included as read by `parse` with `ParseOptions::strict_numbers`, the document being an object or an array like for `parse`, followed only by whitespace.
+    /// Input is read by chunks into a fixed stack buffer and nothing is flattened nor allocated, except for the error. Strings must be UTF-8 and their escape sequences valid,
+    /// nesting is limited to `u8::MAX` like for `parse`. Unlike `parse`, no option is supported: the whole document is validated.
+    pub fn validate_stream<R: Read>(reader: R) -> Result<(), (ParseError, usize)> {
+        JSONParser::validate_stream_with_options(reader, &ParseOptions::default())
+    }
+
+    /// Like `validate_stream`, with limits of `options`, reporting `progress` if any: `max_open_containers` fails with `ParseError::LimitExceeded { limit: "max_open_containers", .. }` as soon as a stream,
+    /// e.g. an adversarial one opening containers without closing them, nests deeper. Other options are ignored.
+    pub fn validate_stream_with_options<R: Read>(mut reader: R, options: &ParseOptions) -> Result<(), (ParseError, usize)> {
+        let max_open_containers = options.max_open_containers.unwrap_or(usize::MAX);
+        let mut validator = Validator { state: State::Root, containers: [0; 4], depth: 0, max_open_containers, offset: 0, token_start: 0 };
+        let mut buffer = [0_u8; BUFFER_LEN];
+        let mut next_progress = 0;
+        loop {
+            if let Some(progress) = options.progress.as_ref().filter(|_| validator.offset >= next_progress) {
+                next_progress = validator.offset + options.progress_interval.max(1);
+                progress(validator.offset, 0);
+            }
+            let read = match reader.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(with_offset(ParseError::Io { kind: e.kind(), offset: validator.offset })),
+            };
+            for byte in buffer[0..read].iter() {
+                validator.feed(*byte).map_err(with_offset)?;
+                validator.offset += 1;
+            }
+        }
+        validator.finish().map_err(with_offset)?;
+        if let Some(ref progress) = options.progress {
+            progress(validator.offset, 0);
+        }
+        Ok(())
+    }
+}
+
+fn with_offset(error: ParseError) -> (ParseError, usize) {
+    let offset = error.offset();
+    (error, offset)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    Root,
+    End,
+    ValueOrClose,
+    Value,
+    KeyOrClose,
+    Key,
+    Colon,
+    CommaOrClose,
+    String { key: bool },
+    Escape { key: bool },
+    Unicode { key: bool, remaining: u8 },
+    // continuation bytes of a multi-byte character, the next one being in `lower..=upper`
+    Utf8 { key: bool, remaining: u8, lower: u8, upper: u8 },
+    Literal { literal: &'static str, matched: u8 },
+    Number(NumberState),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NumberState {
+    Minus,
+    Zero,
+    Integer,
+    Dot,
+    Fraction,
+    Exponent,
+    ExponentSign,
+    ExponentDigits,
+}
+
+impl NumberState {
+    fn is_complete(self) -> bool {
+        matches!(self, NumberState::Zero | NumberState::Integer | NumberState::Fraction | NumberState::ExponentDigits)
+    }
+}
+
+struct Validator {
+    state: State,
+    // one bit per open container, set for an object
+    containers: [u64; 4],
+    depth: usize,
+    max_open_containers: usize,
+    offset: usize,
+    // start of the current number, literal, escape sequence or UTF-8 sequence
+    token_start: usize,
+}
+
+impl Validator {
+    fn feed(&mut self, byte: u8) -> Result<(), ParseError> {
+        self.state = match self.state {
+            State::String { key } => match byte {
+                b'"' if key => State::Colon,
+                b'"' => State::CommaOrClose,
+                b'\\' => {
+                    self.token_start = self.offset;
+                    State::Escape { key }
+                }
+                0..=0x1f => return Err(self.unexpected(byte, "string character")),
+                0x20..=0x7f => State::String { key },
+                _ => {
+                    self.token_start = self.offset;
+                    self.utf8_start(byte, key)?
+                }
+            },
+            State::Escape { key } => match byte {
+                b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't' => State::String { key },
+                b'u' => State::Unicode { key, remaining: 4 },
+                _ => return Err(ParseError::InvalidEscape { offset: self.token_start }),
+            },
+            State::Unicode { key, remaining } => match byte {
+                _ if !byte.is_ascii_hexdigit() => return Err(ParseError::InvalidEscape { offset: self.token_start }),
+                _ if remaining == 1 => State::String { key },
+                _ => State::Unicode { key, remaining: remaining - 1 },
+            },
+            State::Utf8 { key, remaining, lower, upper } => match byte {
+                _ if byte < lower || byte > upper => return Err(ParseError::InvalidUtf8 { offset: self.token_start }),
+                _ if remaining == 1 => State::String { key },
+                _ => State::Utf8 { key, remaining: remaining - 1, lower: 0x80, upper: 0xbf },
+            },
+            State::Literal { literal, matched } => {
+                if literal.as_bytes()[matched as usize] != byte {
+                    return Err(self.unexpected(byte, literal));
+                }
+                if matched as usize + 1 == literal.len() { State::CommaOrClose } else { State::Literal { literal, matched: matched + 1 } }
+            }
+            State::Number(number) => match (number, byte) {
+                (NumberState::Minus, b'0') => State::Number(NumberState::Zero),
+                (NumberState::Minus | NumberState::Integer, b'0'..=b'9') => State::Number(NumberState::Integer),
+                (NumberState::Zero | NumberState::Integer, b'.') => State::Number(NumberState::Dot),
+                (NumberState::Dot | NumberState::Fraction, b'0'..=b'9') => State::Number(NumberState::Fraction),
+                (NumberState::Zero | NumberState::Integer | NumberState::Fraction, b'e' | b'E') => State::Number(NumberState::Exponent),
+                (NumberState::Exponent, b'+' | b'-') => State::Number(NumberState::ExponentSign),
+                (NumberState::Exponent | NumberState::ExponentSign | NumberState::ExponentDigits, b'0'..=b'9') => State::Number(NumberState::ExponentDigits),
+                _ if number.is_complete() && !byte.is_ascii_digit() && byte != b'.' => {
+                    // the byte following the number is read as the one following any value
+                    self.state = State::CommaOrClose;
+                    return self.feed(byte);
+                }
+                _ => return Err(ParseError::InvalidNumber { offset: self.token_start }),
+            },
+            _ if matches!(byte, b' ' | b'\t' | b'\n' | b'\r') => self.state,
+            State::Root => match byte {
+                b'{' | b'[' => self.open(byte)?,
+                _ => return Err(self.unexpected(byte, "'{' or '['")),
+            },
+            State::End => return Err(self.unexpected(byte, "end of input")),
+            State::ValueOrClose if byte == b']' => self.close(),
+            State::ValueOrClose | State::Value => self.value_start(byte)?,
+            State::KeyOrClose if byte == b'}' => self.close(),
+            State::KeyOrClose | State::Key => match byte {
+                b'"' => State::String { key: true },
+                _ => return Err(self.unexpected(byte, "object key")),
+            },
+            State::Colon => match byte {
+                b':' => State::Value,
+                _ => return Err(self.unexpected(byte, "':' after object key")),
+            },
+            State::CommaOrClose => match (byte, self.in_object()) {
+                (b',', true) => State::Key,
+                (b',', false) => State::Value,
+                (b'}', true) | (b']', false) => self.close(),
+                (_, true) => return Err(self.unexpected(byte, "',' or '}' after object value")),
+                (_, false) => return Err(self.unexpected(byte, "',' or ']' after array value")),
+            },
+        };
+        Ok(())
+    }
+
+    fn finish(&self) -> Result<(), ParseError> {
+        match self.state {
+            State::End => Ok(()),
+            _ => Err(ParseError::UnexpectedEof { offset: self.offset }),
+        }
+    }
+
+    fn value_start(&mut self, byte: u8) -> Result<State, ParseError> {
+        self.token_start = self.offset;
+        Ok(match byte {
+            b'{' | b'[' => self.open(byte)?,
+            b'"' => State::String { key: false },
+            b'-' => State::Number(NumberState::Minus),
+            b'0' => State::Number(NumberState::Zero),
+            b'1'..=b'9' => State::Number(NumberState::Integer),
+            b't' => State::Literal { literal: "true", matched: 1 },
+            b'f' => State::Literal { literal: "false", matched: 1 },
+            b'n' => State::Literal { literal: "null", matched: 1 },
+            _ => return Err(self.unexpected(byte, "value")),
+        })
+    }
+
+    fn utf8_start(&self, byte: u8, key: bool) -> Result<State, ParseError> {
+        let (remaining, lower, upper) = match byte {
+            0xc2..=0xdf => (1, 0x80, 0xbf),
+            0xe0 => (2, 0xa0, 0xbf),
+            0xed => (2, 0x80, 0x9f),
+            0xe1..=0xef => (2, 0x80, 0xbf),
+            0xf0 => (3, 0x90, 0xbf),
+            0xf1..=0xf3 => (3, 0x80, 0xbf),
+            0xf4 => (3, 0x80, 0x8f),
+            _ => return Err(ParseError::InvalidUtf8 { offset: self.token_start }),
+        };
+        Ok(State::Utf8 { key, remaining, lower, upper })
+    }
+
+    fn open(&mut self, byte: u8) -> Result<State, ParseError> {
+        if self.depth == self.max_open_containers {
+            return Err(ParseError::LimitExceeded { limit: "max_open_containers", offset: self.offset });
+        }
+        if self.depth == u8::MAX as usize {
+            return Err(ParseError::DepthExceeded { offset: self.offset });
+        }
+        let (word, bit) = (self.depth / 64, self.depth % 64);
+        if byte == b'{' {
+            self.containers[word] |= 1 << bit;
+        } else {
+            self.containers[word] &= !(1 << bit);
+        }
+        self.depth += 1;
+        Ok(if byte == b'{' { State::KeyOrClose } else { State::ValueOrClose })
+    }
+
+    // the closing byte matching the innermost container
+    fn close(&mut self) -> State {
+        self.depth -= 1;
+        if self.depth == 0 { State::End } else { State::CommaOrClose }
+    }
+
+    fn in_object(&self) -> bool {
+        let depth = self.depth - 1;
+        self.containers[depth / 64] & (1 << (depth % 64)) != 0
+    }
+
+    fn unexpected(&self, byte: u8, expected: &'static str) -> ParseError {
+        let found = if byte.is_ascii_graphic() { format!("'{}'", byte as char) } else { format!("byte 0x{:02x}", byte) };
+        ParseError::UnexpectedToken { expected, found, offset: self.offset }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, ErrorKind, Read};
+
+    use crate::error::ParseError;
+    use crate::{JSONParser, ParseOptions};
+
+    fn validate(json: &[u8]) -> Result<(), (ParseError, usize)> {
+        JSONParser::validate_stream(Cursor::new(json))
+    }
+
+    #[test]
+    fn validate_stream() {
+        let valid = [
+            r#"{}"#,
+            r#" [ ] "#,
+            r#"{"a": [1, -0.5, 2e10, 3.25E-2, 0, true, false, null, "b\n\u00e9\"", {"c": {}}], "é😀": "ü"}"#,
+            "[[1], [[2]]]\r\n",
+        ];
+        for json in valid {
+            assert_eq!(validate(json.as_bytes()), Ok(()), "{}", json);
+        }
+        let invalid: [(&str, ParseError); 14] = [
+            ("", ParseError::UnexpectedEof { offset: 0 }),
+            (r#"{"a": [1, 2"#, ParseError::UnexpectedEof { offset: 11 }),
+            ("1", ParseError::UnexpectedToken { expected: "'{' or '['", found: "'1'".to_string(), offset: 0 }),
+            ("{} {}", ParseError::UnexpectedToken { expected: "end of input", found: "'{'".to_string(), offset: 3 }),
+            (r#"{"a" 1}"#, ParseError::UnexpectedToken { expected: "':' after object key", found: "'1'".to_string(), offset: 5 }),
+            (r#"{"a": 1,}"#, ParseError::UnexpectedToken { expected: "object key", found: "'}'".to_string(), offset: 8 }),
+            ("[1, 2}", ParseError::UnexpectedToken { expected: "',' or ']' after array value", found: "'}'".to_string(), offset: 5 }),
+            (r#"{"a": 1]"#, ParseError::UnexpectedToken { expected: "',' or '}' after object value", found: "']'".to_string(), offset: 7 }),
+            ("[tru]", ParseError::UnexpectedToken { expected: "true", found: "']'".to_string(), offset: 4 }),
+            ("[\"a\tb\"]", ParseError::UnexpectedToken { expected: "string character", found: "byte 0x09".to_string(), offset: 3 }),
+            (r#"{"a": 1.2.3}"#, ParseError::InvalidNumber { offset: 6 }),
+            ("[01]", ParseError::InvalidNumber { offset: 1 }),
+            ("[1.]", ParseError::InvalidNumber { offset: 1 }),
+            (r#"["a\x41"]"#, ParseError::InvalidEscape { offset: 3 }),
+        ];
+        for (json, error) in invalid {
+            let offset = error.offset();
+            assert_eq!(validate(json.as_bytes()), Err((error, offset)), "{}", json);
+        }
+        assert_eq!(validate(b"[\"\\u00g0\"]"), Err((ParseError::InvalidEscape { offset: 2 }, 2)));
+        assert_eq!(validate(b"[\"\xc3\x28\"]").unwrap_err(), (ParseError::InvalidUtf8 { offset: 2 }, 2));
+        assert_eq!(validate(b"[\"\xed\xa0\x80\"]").unwrap_err(), (ParseError::InvalidUtf8 { offset: 2 }, 2));
+
+        let nested = "[".repeat(255) + &"]".repeat(255);
+        assert_eq!(validate(nested.as_bytes()), Ok(()));
+        let nested = "[".repeat(300) + &"]".repeat(300);
+        assert_eq!(validate(nested.as_bytes()), Err((ParseError::DepthExceeded { offset: 255 }, 255)));
+    }
+
+    #[test]
+    fn validate_stream_numbers_like_strict_parse() {
+        let numbers = [
+            "0", "-0", "1", "-12", "1.5", "0.25", "1e5", "1E5", "1.5E-3", "2e+10", "-0.5e0",
+            "01", "-01", "00", "1.", ".5", "-", "+1", "1e", "1e+", "1.2.3", "1e5e5", "1-2", "--1", "0x1",
+        ];
+        for number in numbers {
+            let json = format!("[{}]", number);
+            let validated = validate(json.as_bytes()).map_err(|(error, _)| error);
+            let parsed = JSONParser::parse(&json, ParseOptions::default().strict_numbers(true)).map(|_| ());
+            assert_eq!(validated.is_ok(), parsed.is_ok(), "{}: {:?} {:?}", json, validated, parsed);
+            if let (Err(ParseError::InvalidNumber { offset }), Err(error)) = (&validated, &parsed) {
+                assert_eq!(*offset, error.offset(), "{}", json);
+            }
+        }
+    }
+
+    #[test]
+    fn validate_stream_chunks() {
+        // one byte per read, so that every token spans several chunks
+        struct ByteByByte<'a>(&'a [u8]);
+        impl Read for ByteByByte<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                let Some((first, rest)) = self.0.split_first() else {
+                    return Ok(0);
+                };
+                buf[0] = *first;
+                self.0 = rest;
+                Ok(1)
+            }
+        }
+        let json = r#"{"key": ["value \"é\" \u0041", 12.5e-3, true, null, {"nested": [false]}]}"#;
+        assert_eq!(JSONParser::validate_stream(ByteByByte(json.as_bytes())), Ok(()));
+        assert_eq!(JSONParser::validate_stream(ByteByByte(&json.as_bytes()[0..30])), Err((ParseError::UnexpectedEof { offset: 30 }, 30)));
+
+        let large = format!("[{}1]", r#"{"id": 1, "name": "abc"}, "#.repeat(2000));
+        assert!(large.len() > 2 * super::BUFFER_LEN);
+        assert_eq!(validate(large.as_bytes()), Ok(()));
+        let offset = large.len() - 2;
+        let mut invalid = large.into_bytes();
+        invalid[offset] = b'x';
+        assert_eq!(validate(&invalid).unwrap_err().1, offset);
+    }
+
+    #[test]
+    fn validate_stream_read_error() {
+        struct Failing(usize);
+        impl Read for Failing {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                if self.0 == 0 {
+                    return Err(ErrorKind::ConnectionReset.into());
+                }
+                buf[0] = b'[';
+                self.0 -= 1;
+                Ok(1)
+            }
+        }
+        assert_eq!(JSONParser::validate_stream(Failing(3)), Err((ParseError::Io { kind: ErrorKind::ConnectionReset, offset: 3 }, 3)));
+    }
+
+    #[test]
+    fn validate_stream_max_open_containers() {
+        // never closes its arrays
+        struct Nesting;
+        impl Read for Nesting {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                buf.fill(b'[');
+                Ok(buf.len())
+            }
+        }
+        let options = ParseOptions::default().max_open_containers(100);
+        assert_eq!(JSONParser::validate_stream_with_options(Nesting, &options), Err((ParseError::LimitExceeded { limit: "max_open_containers", offset: 100 }, 100)));
+        assert_eq!(JSONParser::validate_stream(Nesting), Err((ParseError::DepthExceeded { offset: 255 }, 255)));
+        assert_eq!(JSONParser::validate_stream_with_options(Cursor::new(r#"{"a": [[1]]}"#), &ParseOptions::default().max_open_containers(3)), Ok(()));
+        assert_eq!(JSONParser::validate_stream_with_options(Cursor::new(r#"{"a": [[1]]}"#), &ParseOptions::default().max_open_containers(2)).unwrap_err().1, 7);
+    }
+}